@@ -1,10 +1,11 @@
+use crate::buff::{Buff, BuffCategory, BuffLedger};
+use serde::{Deserialize, Serialize};
 use std::{
-    cell::{Ref, RefCell, RefMut},
     fmt::{self, Display, Formatter},
-    rc::Rc,
+    sync::{Arc, Mutex, MutexGuard},
 };
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Ability {
     Strength(i32),
     Dexterity(i32),
@@ -14,7 +15,51 @@ pub enum Ability {
     Intelligence(i32),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbilityName {
+    Strength,
+    Dexterity,
+    Stamina,
+    Endurement,
+    Luck,
+    Intelligence,
+}
+
+impl AbilityName {
+    pub const ALL: [AbilityName; 6] = [
+        Self::Strength,
+        Self::Dexterity,
+        Self::Stamina,
+        Self::Endurement,
+        Self::Luck,
+        Self::Intelligence,
+    ];
+
+    pub fn ability(&self, value: i32) -> Ability {
+        match self {
+            Self::Strength => Ability::Strength(value),
+            Self::Dexterity => Ability::Dexterity(value),
+            Self::Stamina => Ability::Stamina(value),
+            Self::Endurement => Ability::Endurement(value),
+            Self::Luck => Ability::Luck(value),
+            Self::Intelligence => Ability::Intelligence(value),
+        }
+    }
+}
+
 impl Ability {
+    pub fn name(&self) -> AbilityName {
+        match &self {
+            Self::Strength(_) => AbilityName::Strength,
+            Self::Dexterity(_) => AbilityName::Dexterity,
+            Self::Stamina(_) => AbilityName::Stamina,
+            Self::Endurement(_) => AbilityName::Endurement,
+            Self::Luck(_) => AbilityName::Luck,
+            Self::Intelligence(_) => AbilityName::Intelligence,
+        }
+    }
+
     pub fn typename(&self) -> String {
         String::from(match &self {
             Self::Strength(_) => "Strength",
@@ -37,153 +82,319 @@ impl Ability {
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct AbilityModifier {
     ability: Ability,
-    modifier_positive: [i32; 2],
-    modifier_negative: [i32; 2],
+    ledger: BuffLedger,
     current: Option<i32>,
+    // counts the buffs from apply_positive and apply_negative to name each one apart
+    legacy: u32,
 }
 
 impl From<Ability> for AbilityModifier {
     fn from(ability: Ability) -> Self {
         Self {
             ability,
-            modifier_positive: [0; 2],
-            modifier_negative: [0; 2],
+            ledger: BuffLedger::new(),
             current: Option::None,
+            legacy: 0,
         }
     }
 }
 
 impl AbilityModifier {
-    pub fn apply_positive(&mut self, modifier: i32) {
-        if modifier >= self.modifier_positive[0] {
-            self.modifier_positive = [modifier, self.modifier_positive[0]];
-            self.current = None;
-        } else if modifier >= self.modifier_positive[1] {
-            self.modifier_positive = [self.modifier_positive[0], modifier];
+    pub fn ability(&self) -> &Ability {
+        &self.ability
+    }
+
+    pub fn ledger(&self) -> &BuffLedger {
+        &self.ledger
+    }
+
+    pub fn apply(&mut self, buff: Buff) {
+        self.ledger.apply(buff);
+        self.current = None;
+    }
+
+    pub fn remove(&mut self, source: &str) -> bool {
+        let removed = self.ledger.remove(source);
+        if removed {
             self.current = None;
         }
+        removed
     }
 
-    pub fn apply_negative(&mut self, modifier: i32) {
-        let mut modifier = modifier;
-        if modifier > 0 {
-            modifier = (-1) * modifier;
-        }
-        if modifier <= self.modifier_negative[0] {
-            self.modifier_negative = [modifier, self.modifier_negative[0]];
-            self.current = None;
-        } else if modifier <= self.modifier_negative[1] {
-            self.modifier_negative = [self.modifier_negative[0], modifier];
+    pub fn tick(&mut self) -> usize {
+        let expired = self.ledger.tick();
+        if expired > 0 {
             self.current = None;
         }
+        expired
+    }
+
+    fn legacy_source(&mut self) -> String {
+        self.legacy += 1;
+        format!("legacy#{}", self.legacy)
+    }
+
+    // anything but a gain is dropped, as it never won a slot before the ledger
+    pub fn apply_positive(&mut self, modifier: i32) {
+        if modifier <= 0 {
+            return;
+        }
+        let source = self.legacy_source();
+        self.apply(Buff::new(
+            &source,
+            BuffCategory::SkillBuff,
+            modifier as f32,
+            None,
+        ));
+    }
+
+    // a positive value counts as the same loss
+    pub fn apply_negative(&mut self, modifier: i32) {
+        let modifier = if modifier > 0 { -modifier } else { modifier };
+        let source = self.legacy_source();
+        self.apply(Buff::new(
+            &source,
+            BuffCategory::SkillBuff,
+            modifier as f32,
+            None,
+        ));
     }
 
     pub fn value(&mut self) -> i32 {
         if let Some(val) = self.current {
             return val;
         }
-        let val = self.ability.value()
-            + self.modifier_positive.iter().sum::<i32>()
-            + self.modifier_negative.iter().sum::<i32>();
+        let val = self.ability.value() + self.ledger.total().round() as i32;
         self.current = Some(val);
         val
     }
 }
 
+// shared between the models reading it, and safe to send across threads
+pub type ModifierCell = Arc<Mutex<AbilityModifier>>;
+
 pub struct AbilityModifierHelper {
-    wrapper: Rc<RefCell<AbilityModifier>>,
+    wrapper: ModifierCell,
+}
+
+pub fn lock(cell: &ModifierCell) -> MutexGuard<'_, AbilityModifier> {
+    cell.lock()
+        .expect("ability modifier lock should not be poisoned")
 }
 
 impl AbilityModifierHelper {
-    pub fn get_cell(&self) -> Rc<RefCell<AbilityModifier>> {
+    pub fn get_cell(&self) -> ModifierCell {
         self.wrapper.clone()
     }
 
-    pub fn get_mut(&self) -> RefMut<'_, AbilityModifier> {
-        self.wrapper.borrow_mut()
+    pub fn get_mut(&self) -> MutexGuard<'_, AbilityModifier> {
+        lock(&self.wrapper)
     }
 
-    pub fn get(&self) -> Ref<'_, AbilityModifier> {
-        self.wrapper.borrow()
+    // a snapshot, so no lock is held while the caller reads it
+    pub fn get(&self) -> AbilityModifier {
+        lock(&self.wrapper).clone()
+    }
+
+    // a helper with its own copy of the modifier
+    pub fn detached(&self) -> Self {
+        self.get().into()
     }
 }
 
 impl From<AbilityModifier> for AbilityModifierHelper {
     fn from(value: AbilityModifier) -> Self {
         Self {
-            wrapper: Rc::new(RefCell::new(value)),
+            wrapper: Arc::new(Mutex::new(value)),
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AbilityModelType {
     Equal,
     WeigthedOnPrior,
     Single,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    #[default]
+    Truncate,
+    Floor,
+    Round,
+    Ceil,
+}
+
+// the abilities and weights of a model, built into one over a member's modifiers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelFormula {
+    weights: Vec<(AbilityName, u32)>,
+    rounding: Rounding,
+}
+
 pub struct AbilityModel {
-    model_type: AbilityModelType,
-    ability1: Rc<RefCell<AbilityModifier>>,
-    ability2: Option<Rc<RefCell<AbilityModifier>>>,
+    weights: Vec<(ModifierCell, u32)>,
+    rounding: Rounding,
+}
+
+impl Rounding {
+    pub fn divide(&self, value: i32, divisor: i32) -> i32 {
+        match self {
+            Self::Truncate => value / divisor,
+            Self::Floor => value.div_euclid(divisor),
+            Self::Round => (value as f64 / divisor as f64).round() as i32,
+            Self::Ceil => (value as f64 / divisor as f64).ceil() as i32,
+        }
+    }
+}
+
+impl AbilityModelType {
+    pub fn weights(&self) -> &'static [u32] {
+        match self {
+            Self::Single => &[1],
+            Self::Equal => &[1, 1],
+            Self::WeigthedOnPrior => &[2, 1],
+        }
+    }
 }
 
 impl AbilityModel {
     pub fn new(
         model_type: AbilityModelType,
-        ability1: Rc<RefCell<AbilityModifier>>,
-        ability2: Option<Rc<RefCell<AbilityModifier>>>,
+        ability1: ModifierCell,
+        ability2: Option<ModifierCell>,
     ) -> Result<Self, AbilityModelType> {
-        match model_type {
-            AbilityModelType::Single => Ok(Self {
-                model_type,
-                ability1,
-                ability2,
-            }),
-            _ => match ability2 {
-                None => Err(model_type),
-                Some(_) => Ok(Self {
-                    model_type,
-                    ability1,
-                    ability2,
-                }),
-            },
+        let abilities = match (model_type, ability2) {
+            (AbilityModelType::Single, _) => vec![ability1],
+            (_, Some(ability2)) => vec![ability1, ability2],
+            (_, None) => return Err(model_type),
+        };
+        let weights = abilities
+            .into_iter()
+            .zip(model_type.weights().iter().copied())
+            .collect();
+        Ok(Self {
+            weights,
+            rounding: Rounding::Truncate,
+        })
+    }
+
+    // None when there is nothing to weigh
+    pub fn weighted(weights: Vec<(ModifierCell, u32)>, rounding: Rounding) -> Option<Self> {
+        if weights.iter().map(|(_, weight)| weight).sum::<u32>() == 0 {
+            return None;
+        }
+        Some(Self { weights, rounding })
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    pub fn cells(&self) -> Vec<ModifierCell> {
+        self.weights.iter().map(|(cell, _)| cell.clone()).collect()
+    }
+
+    // a model over its own copies of the modifiers, keeping any sharing within it
+    pub fn detached(&self) -> Self {
+        self.detached_with(&mut vec![])
+    }
+
+    // copies are kept as (original, copy) pairs, pass the same list to models that
+    // share modifiers so their copies are shared too
+    pub fn detached_with(&self, copies: &mut Vec<(ModifierCell, ModifierCell)>) -> Self {
+        let weights = self
+            .weights
+            .iter()
+            .map(|(cell, weight)| {
+                let copy = match copies.iter().find(|(known, _)| Arc::ptr_eq(known, cell)) {
+                    Some((_, copy)) => copy.clone(),
+                    None => {
+                        let copy = Arc::new(Mutex::new(lock(cell).clone()));
+                        copies.push((cell.clone(), copy.clone()));
+                        copy
+                    }
+                };
+                (copy, *weight)
+            })
+            .collect();
+        Self {
+            weights,
+            rounding: self.rounding,
         }
     }
 
     pub fn value(&self) -> i32 {
-        match &self.model_type {
-            &AbilityModelType::Single => self.ability1.borrow_mut().value(),
-            &AbilityModelType::Equal => {
-                (self.ability1.borrow_mut().value()
-                    + self
-                        .ability2
-                        .as_ref()
-                        .expect("ability2 should exist")
-                        .borrow_mut()
-                        .value())
-                    / 2
-            }
-            &AbilityModelType::WeigthedOnPrior => {
-                (self.ability1.borrow_mut().value() * 2
-                    + self
-                        .ability2
-                        .as_ref()
-                        .expect("ability2 should exist")
-                        .borrow_mut()
-                        .value())
-                    / 3
-            }
+        let total = self
+            .weights
+            .iter()
+            .map(|(cell, weight)| lock(cell).value() * *weight as i32)
+            .sum::<i32>();
+        let divisor = self.weights.iter().map(|(_, weight)| weight).sum::<u32>();
+        self.rounding.divide(total, divisor as i32)
+    }
+}
+
+impl ModelFormula {
+    pub fn single(ability: AbilityName) -> Self {
+        Self {
+            weights: vec![(ability, 1)],
+            rounding: Rounding::Truncate,
+        }
+    }
+
+    pub fn preset(
+        model_type: AbilityModelType,
+        primary: AbilityName,
+        secondary: Option<AbilityName>,
+    ) -> Result<Self, AbilityModelType> {
+        let abilities = match (model_type, secondary) {
+            (AbilityModelType::Single, _) => vec![primary],
+            (_, Some(secondary)) => vec![primary, secondary],
+            (_, None) => return Err(model_type),
+        };
+        Ok(Self {
+            weights: abilities
+                .into_iter()
+                .zip(model_type.weights().iter().copied())
+                .collect(),
+            rounding: Rounding::Truncate,
+        })
+    }
+
+    // None when there is nothing to weigh
+    pub fn weighted(weights: Vec<(AbilityName, u32)>, rounding: Rounding) -> Option<Self> {
+        if weights.iter().map(|(_, weight)| weight).sum::<u32>() == 0 {
+            return None;
         }
+        Some(Self { weights, rounding })
+    }
+
+    pub fn weights(&self) -> &[(AbilityName, u32)] {
+        &self.weights
+    }
+
+    pub fn rounding(&self) -> Rounding {
+        self.rounding
+    }
+
+    pub fn pair(
+        model_type: AbilityModelType,
+        primary: AbilityName,
+        secondary: AbilityName,
+    ) -> Self {
+        Self::preset(model_type, primary, Some(secondary)).expect("a pair has two abilities")
     }
 }
 
 impl Display for Ability {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "\t{}: {}\n", self.typename(), self.value())
+        writeln!(f, "\t{}: {}", self.typename(), self.value())
     }
 }
 
@@ -191,12 +402,10 @@ impl Display for AbilityModifier {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
             f,
-            "[{}, modifiers: {},{},{},{}] -> {}",
+            "[{}, modifiers: {},{}] -> {}",
             self.ability,
-            self.modifier_positive[0],
-            self.modifier_positive[1],
-            self.modifier_negative[0],
-            self.modifier_negative[1],
+            self.ledger.positive(),
+            self.ledger.negative(),
             self.current
                 .map_or(String::from("<Lazy>"), |val| format!("{}", val))
         )
@@ -214,10 +423,9 @@ mod tests {
 
     #[test]
     fn biased_ability() {
-        let ability_stamina: Rc<RefCell<AbilityModifier>> =
-            Rc::new(RefCell::new(Ability::Stamina(100).into()));
-        let ability_endurement: Rc<RefCell<AbilityModifier>> =
-            Rc::new(RefCell::new(Ability::Endurement(160).into()));
+        let ability_stamina: ModifierCell = Arc::new(Mutex::new(Ability::Stamina(100).into()));
+        let ability_endurement: ModifierCell =
+            Arc::new(Mutex::new(Ability::Endurement(160).into()));
         let defense = AbilityModel::new(
             AbilityModelType::WeigthedOnPrior,
             ability_stamina.clone(),
@@ -225,26 +433,84 @@ mod tests {
         )
         .expect("it should succeed.");
         assert_eq!(defense.value(), 120);
-        ability_stamina.borrow_mut().apply_positive(60);
+        ability_stamina.lock().unwrap().apply_positive(60);
         assert_eq!(defense.value(), 160);
-        ability_endurement.borrow_mut().apply_negative(60);
+        ability_endurement.lock().unwrap().apply_negative(60);
         assert_eq!(defense.value(), 140);
     }
 
     #[test]
     fn multiple_modifier() {
-        let ability_intelligence: Rc<RefCell<AbilityModifier>> =
-            Rc::new(RefCell::new(Ability::Intelligence(100).into()));
+        let ability_intelligence: ModifierCell =
+            Arc::new(Mutex::new(Ability::Intelligence(100).into()));
         let buff = AbilityModel::new(AbilityModelType::Single, ability_intelligence.clone(), None)
             .expect("it should succeed");
         assert_eq!(buff.value(), 100);
-        ability_intelligence.borrow_mut().apply_positive(30);
-        ability_intelligence.borrow_mut().apply_positive(50);
-        ability_intelligence.borrow_mut().apply_positive(40);
+        ability_intelligence.lock().unwrap().apply_positive(30);
+        ability_intelligence.lock().unwrap().apply_positive(50);
+        ability_intelligence.lock().unwrap().apply_positive(40);
         assert_eq!(buff.value(), 190);
-        ability_intelligence.borrow_mut().apply_negative(-40);
-        ability_intelligence.borrow_mut().apply_negative(-50);
-        ability_intelligence.borrow_mut().apply_negative(-60);
+        ability_intelligence.lock().unwrap().apply_negative(-40);
+        ability_intelligence.lock().unwrap().apply_negative(-50);
+        ability_intelligence.lock().unwrap().apply_negative(-60);
         assert_eq!(buff.value(), 80);
     }
+
+    #[test]
+    fn expiring_modifier() {
+        let mut modifier = AbilityModifier::from(Ability::Strength(200));
+        modifier.apply(Buff::new(
+            "attack up",
+            BuffCategory::SkillBuff,
+            50.0,
+            Some(2),
+        ));
+        modifier.apply(Buff::new("ring", BuffCategory::Accessory, 10.0, None));
+        assert_eq!(modifier.value(), 260);
+        modifier.tick();
+        assert_eq!(modifier.value(), 260);
+        modifier.tick();
+        assert_eq!(modifier.value(), 210);
+        assert!(modifier.remove("ring"));
+        assert_eq!(modifier.value(), 200);
+        modifier.apply_positive(30);
+        modifier.apply_positive(20);
+        assert!(modifier.remove("legacy#1"));
+        assert_eq!(modifier.value(), 220);
+        // a negative value is no gain
+        modifier.apply_positive(-10);
+        assert_eq!(modifier.value(), 220);
+        assert!(!modifier.remove("legacy#3"));
+    }
+
+    #[test]
+    fn weighted_ability() {
+        let cells: Vec<ModifierCell> = [
+            Ability::Strength(100),
+            Ability::Dexterity(101),
+            Ability::Intelligence(101),
+        ]
+        .into_iter()
+        .map(|ability| Arc::new(Mutex::new(ability.into())))
+        .collect();
+        let even = AbilityModel::weighted(
+            cells.iter().map(|cell| (cell.clone(), 1)).collect(),
+            Rounding::Truncate,
+        )
+        .expect("it should succeed");
+        assert_eq!(even.value(), 100);
+        let rounded = AbilityModel::weighted(
+            cells.iter().map(|cell| (cell.clone(), 1)).collect(),
+            Rounding::Ceil,
+        )
+        .expect("it should succeed");
+        assert_eq!(rounded.value(), 101);
+        let biased = AbilityModel::weighted(
+            vec![(cells[0].clone(), 3), (cells[1].clone(), 1)],
+            Rounding::Round,
+        )
+        .expect("it should succeed");
+        assert_eq!(biased.value(), 100);
+        assert!(AbilityModel::weighted(vec![(cells[0].clone(), 0)], Rounding::Round).is_none());
+    }
 }
@@ -0,0 +1,1623 @@
+use crate::buff::{Buff, BuffCategory};
+use crate::character::Character;
+use crate::critical::Critical;
+use crate::field::{Battlefield, Layer};
+use crate::formation::Formation;
+use crate::overdrive::{Overdrive, OD_LEVELS};
+use crate::passive::Trigger;
+use crate::pattern::{EnemyAction, Targeting};
+use crate::sampling::Rng;
+use crate::skill::{DamageCalculator, EffectKind, Pool, Scope, Skill};
+use crate::target::{Enemy, Hit};
+use crate::targets::{Outcome, Targets};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+pub const FRONT_SIZE: usize = 3;
+pub const PARTY_SIZE: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Skill {
+        actor: usize,
+        skill: usize,
+        target: usize,
+        ally: usize,
+    },
+    // where it sits among the actions decides whether the SP boost lands before
+    // the skills or the gauge they fill can be spent first
+    Overdrive {
+        level: u32,
+    },
+    // trades a front member for a back one, SwapCost decides whether the slot still acts
+    Swap {
+        out: usize,
+        into: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapCost {
+    // the slot keeps its action for the turn
+    #[default]
+    Free,
+    // the swap is the action of the slot
+    Action,
+}
+
+pub trait Rotation {
+    fn actions(&mut self, turn: u32) -> Vec<Action>;
+
+    // the extra-th overdrive turn played before the enemies of the turn act
+    fn overdrive_actions(&mut self, _turn: u32, _extra: u32) -> Vec<Action> {
+        vec![]
+    }
+}
+
+// plays the scripted turns in order and starts over after the last one
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedRotation {
+    turns: Vec<Vec<Action>>,
+    overdrive: Vec<Vec<Vec<Action>>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Skill {
+        actor: String,
+        skill: String,
+        cost: i32,
+        // None for a skill that only supports the party
+        target: Option<String>,
+        hits: Vec<Hit>,
+    },
+    Sp {
+        target: String,
+        amount: i32,
+        sp: i32,
+    },
+    Refused {
+        actor: String,
+        skill: String,
+        cost: i32,
+        sp: i32,
+    },
+    Buff {
+        target: String,
+        effect: String,
+        amount: f32,
+        duration: Option<u32>,
+    },
+    Heal {
+        target: String,
+        pool: Pool,
+        amount: f32,
+        current: f32,
+    },
+    EnemySkill {
+        actor: String,
+        skill: String,
+        target: String,
+        hits: Vec<Hit>,
+    },
+    Knockout {
+        target: String,
+    },
+    Break {
+        target: String,
+    },
+    DpRestored {
+        target: String,
+        dp: f32,
+    },
+    Overdrive {
+        level: u32,
+        gauge: f32,
+        sp: i32,
+    },
+    OverdriveRefused {
+        level: u32,
+        gauge: f32,
+    },
+    OverdriveTurn {
+        extra: u32,
+    },
+    Swap {
+        out: String,
+        into: String,
+    },
+    Passive {
+        actor: String,
+        skill: String,
+        hits: Vec<Hit>,
+    },
+    Zone {
+        zone: String,
+        layer: Layer,
+        rate: f32,
+        duration: Option<u32>,
+        replaced: Option<String>,
+    },
+    ZoneFaded {
+        zone: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TurnLog {
+    pub turn: u32,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct BattleLog {
+    pub turns: Vec<TurnLog>,
+    pub kill_turn: Option<u32>,
+    pub wipe_turn: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BattleError {
+    EmptyParty,
+    PartyTooLarge(usize),
+    NoEnemy,
+    NotInFront(usize),
+    AlreadyActed(usize),
+    NoSkill { actor: usize, skill: usize },
+    NoTarget(usize),
+    NoAlly(usize),
+    InvalidOverdrive(u32),
+    InvalidSwap { out: usize, into: usize },
+    Unplaced(usize),
+    NoVitals(usize),
+    NotInParty(usize),
+}
+
+#[derive(Clone)]
+pub struct Battle {
+    party: Vec<Character>,
+    formation: Formation,
+    enemies: Vec<Enemy>,
+    calculator: DamageCalculator,
+    critical: Critical,
+    rng: Option<Rng>,
+    overdrive: Overdrive,
+    battlefield: Battlefield,
+    swap_cost: SwapCost,
+    turn: u32,
+}
+
+impl Action {
+    pub fn skill(actor: usize, skill: usize, target: usize) -> Self {
+        Self::Skill {
+            actor,
+            skill,
+            target,
+            ally: actor,
+        }
+    }
+
+    pub fn support(actor: usize, skill: usize, ally: usize) -> Self {
+        Self::Skill {
+            actor,
+            skill,
+            target: 0,
+            ally,
+        }
+    }
+}
+
+impl ScriptedRotation {
+    pub fn new() -> Self {
+        Self {
+            turns: vec![],
+            overdrive: vec![],
+        }
+    }
+
+    pub fn add_turn(&mut self, actions: Vec<Action>) {
+        self.turns.push(actions);
+        self.overdrive.push(vec![]);
+    }
+
+    // the next overdrive turn of the last added turn
+    pub fn add_overdrive_turn(&mut self, actions: Vec<Action>) {
+        if self.turns.is_empty() {
+            self.add_turn(vec![]);
+        }
+        if let Some(overdrive) = self.overdrive.last_mut() {
+            overdrive.push(actions);
+        }
+    }
+}
+
+impl Rotation for ScriptedRotation {
+    fn actions(&mut self, turn: u32) -> Vec<Action> {
+        if self.turns.is_empty() {
+            return vec![];
+        }
+        let index = (turn.max(1) - 1) as usize % self.turns.len();
+        self.turns[index].clone()
+    }
+
+    fn overdrive_actions(&mut self, turn: u32, extra: u32) -> Vec<Action> {
+        if self.turns.is_empty() {
+            return vec![];
+        }
+        let index = (turn.max(1) - 1) as usize % self.turns.len();
+        self.overdrive[index]
+            .get((extra.max(1) - 1) as usize)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Battle {
+    pub fn new(party: Vec<Character>, enemies: Vec<Enemy>) -> Result<Self, BattleError> {
+        if party.is_empty() {
+            return Err(BattleError::EmptyParty);
+        }
+        if party.len() > PARTY_SIZE {
+            return Err(BattleError::PartyTooLarge(party.len()));
+        }
+        if enemies.is_empty() {
+            return Err(BattleError::NoEnemy);
+        }
+        // enemy hits need HP to land on
+        let attacks = enemies.iter().any(|enemy| {
+            enemy.attack().is_some()
+                && enemy
+                    .skills()
+                    .iter()
+                    .flat_map(Skill::effects)
+                    .any(|effect| effect.kind() == EffectKind::Damage)
+        });
+        if let Some(member) = party
+            .iter()
+            .position(|member| attacks && member.vitals().max_hp() <= 0.0)
+        {
+            return Err(BattleError::NoVitals(member));
+        }
+        Ok(Self {
+            formation: Formation::new(party.len()),
+            party,
+            enemies,
+            calculator: DamageCalculator::new(),
+            critical: Critical::new(),
+            rng: None,
+            overdrive: Overdrive::default(),
+            battlefield: Battlefield::new(),
+            swap_cost: SwapCost::default(),
+            turn: 0,
+        })
+    }
+
+    pub fn party(&self) -> &[Character] {
+        &self.party
+    }
+
+    pub fn formation(&self) -> &Formation {
+        &self.formation
+    }
+
+    // every member needs a slot, the party order fills the slots by default
+    pub fn set_formation(&mut self, formation: Formation) -> Result<(), BattleError> {
+        if let Some(member) = (0..self.party.len()).find(|&member| formation.slot(member).is_none())
+        {
+            return Err(BattleError::Unplaced(member));
+        }
+        let mut placed = formation.front();
+        placed.extend(formation.back());
+        if let Some(&member) = placed.iter().find(|&&member| member >= self.party.len()) {
+            return Err(BattleError::NotInParty(member));
+        }
+        self.formation = formation;
+        Ok(())
+    }
+
+    pub fn enemies(&self) -> &[Enemy] {
+        &self.enemies
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    pub fn set_calculator(&mut self, calculator: DamageCalculator) {
+        self.calculator = calculator;
+    }
+
+    pub fn set_critical(&mut self, critical: Critical) {
+        self.critical = critical;
+    }
+
+    // spread and criticals are rolled from rng, expected values are used without one
+    pub fn set_rng(&mut self, rng: Option<Rng>) {
+        self.rng = rng;
+    }
+
+    pub fn swap_cost(&self) -> SwapCost {
+        self.swap_cost
+    }
+
+    pub fn set_swap_cost(&mut self, swap_cost: SwapCost) {
+        self.swap_cost = swap_cost;
+    }
+
+    pub fn overdrive(&self) -> &Overdrive {
+        &self.overdrive
+    }
+
+    pub fn set_overdrive(&mut self, overdrive: Overdrive) {
+        self.overdrive = overdrive;
+    }
+
+    pub fn battlefield(&self) -> &Battlefield {
+        &self.battlefield
+    }
+
+    // zones already in place when the battle starts
+    pub fn set_battlefield(&mut self, battlefield: Battlefield) {
+        self.battlefield = battlefield;
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.enemies.iter().all(|enemy| enemy.is_dead())
+    }
+
+    // only members with HP can be knocked out
+    pub fn is_lost(&self) -> bool {
+        self.party.iter().all(|member| member.is_dead())
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.is_won() || self.is_lost()
+    }
+
+    fn standing(&self) -> Vec<usize> {
+        self.formation
+            .front()
+            .into_iter()
+            .filter(|&index| !self.party[index].is_dead())
+            .collect()
+    }
+
+    // swaps are played on a copy of the formation, each front slot acts once a turn
+    fn validate(&self, actions: &[Action]) -> Result<(), BattleError> {
+        let mut formation = self.formation.clone();
+        let mut acted = vec![];
+        for action in actions {
+            match *action {
+                Action::Skill {
+                    actor,
+                    skill,
+                    target,
+                    ally,
+                } => {
+                    let slot = formation
+                        .slot(actor)
+                        .filter(|&slot| slot < FRONT_SIZE)
+                        .ok_or(BattleError::NotInFront(actor))?;
+                    if acted.contains(&slot) {
+                        return Err(BattleError::AlreadyActed(actor));
+                    }
+                    acted.push(slot);
+                    if skill >= self.party[actor].skills().len() {
+                        return Err(BattleError::NoSkill { actor, skill });
+                    }
+                    if target >= self.enemies.len() {
+                        return Err(BattleError::NoTarget(target));
+                    }
+                    if ally >= self.party.len() {
+                        return Err(BattleError::NoAlly(ally));
+                    }
+                }
+                Action::Overdrive { level } => {
+                    if level == 0 || level > OD_LEVELS {
+                        return Err(BattleError::InvalidOverdrive(level));
+                    }
+                }
+                Action::Swap { out, into } => {
+                    let valid = formation.is_front(out)
+                        && formation.slot(into).is_some()
+                        && !formation.is_front(into)
+                        && !self.party[into].is_dead();
+                    if !valid {
+                        return Err(BattleError::InvalidSwap { out, into });
+                    }
+                    if self.swap_cost == SwapCost::Action {
+                        let slot = formation.slot(out).unwrap_or_default();
+                        if acted.contains(&slot) {
+                            return Err(BattleError::AlreadyActed(out));
+                        }
+                        acted.push(slot);
+                    }
+                    formation.swap(out, into);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // a dead target passes the action on to the first enemy still standing
+    fn retarget(&self, target: usize) -> Option<usize> {
+        if !self.enemies[target].is_dead() {
+            return Some(target);
+        }
+        self.enemies.iter().position(|enemy| !enemy.is_dead())
+    }
+
+    fn perform(&mut self, action: Action) -> Vec<Event> {
+        match action {
+            Action::Skill {
+                actor,
+                skill,
+                target,
+                ally,
+            } => {
+                let Some(target) = self.retarget(target) else {
+                    return vec![];
+                };
+                if self.party[actor].is_dead() {
+                    return vec![];
+                }
+                let member = &mut self.party[actor];
+                let skill = member.skills()[skill].clone();
+                let cost = member.sp().cost(skill.sp_cost());
+                if !member.sp_mut().pay(skill.sp_cost()) {
+                    return vec![Event::Refused {
+                        actor: member.name().to_owned(),
+                        skill: skill.name().to_owned(),
+                        cost,
+                        sp: member.sp().current(),
+                    }];
+                }
+                let (struck, mut events) = self.execute(actor, &skill, target, ally);
+                // passives answer the enemy that was crit or broken
+                for (enemy, dealt) in struck.iter() {
+                    if dealt.iter().any(|hit| hit.critical) {
+                        events.extend(
+                            self.fire(actor, *enemy, |trigger| trigger == Trigger::Critical),
+                        );
+                    }
+                    if dealt.iter().any(|hit| hit.broke) {
+                        events.extend(self.fire_all(*enemy, |trigger| trigger == Trigger::Break));
+                    }
+                }
+                let hits = struck.into_iter().flat_map(|(_, hits)| hits).collect();
+                events.insert(
+                    0,
+                    Event::Skill {
+                        actor: self.party[actor].name().to_owned(),
+                        skill: skill.name().to_owned(),
+                        cost,
+                        target: skill
+                            .targets_enemy()
+                            .then(|| self.enemies[target].target().name().to_owned()),
+                        hits,
+                    },
+                );
+                events
+            }
+            Action::Overdrive { level } => match self.overdrive.activate(level) {
+                Some(sp) => {
+                    for index in self.standing() {
+                        self.party[index].sp_mut().gain(sp);
+                    }
+                    let mut events = vec![Event::Overdrive {
+                        level,
+                        gauge: self.overdrive.gauge(),
+                        sp,
+                    }];
+                    events.extend(self.fire_all(0, |trigger| trigger == Trigger::Overdrive));
+                    events
+                }
+                None => vec![Event::OverdriveRefused {
+                    level,
+                    gauge: self.overdrive.gauge(),
+                }],
+            },
+            Action::Swap { out, into } => self.swap(out, into),
+        }
+    }
+
+    fn swap(&mut self, out: usize, into: usize) -> Vec<Event> {
+        self.formation.swap(out, into);
+        let mut events = vec![Event::Swap {
+            out: self.party[out].name().to_owned(),
+            into: self.party[into].name().to_owned(),
+        }];
+        events.extend(self.fire(into, 0, |trigger| trigger == Trigger::SwapIn));
+        events
+    }
+
+    // the first back member standing takes the place of a knocked out front member
+    fn step_up(&mut self, member: usize) -> Vec<Event> {
+        if !self.formation.is_front(member) || !self.party[member].is_dead() {
+            return vec![];
+        }
+        match self
+            .formation
+            .back()
+            .into_iter()
+            .find(|&index| !self.party[index].is_dead())
+        {
+            Some(into) => self.swap(member, into),
+            None => vec![],
+        }
+    }
+
+    // the member's passives answering the trigger, aimed at the enemy behind it or the
+    // first one standing once it is down. passives never set off other passives
+    fn fire(
+        &mut self,
+        member: usize,
+        enemy: usize,
+        answers: impl Fn(Trigger) -> bool,
+    ) -> Vec<Event> {
+        let Some(target) = self.retarget(enemy) else {
+            return vec![];
+        };
+        let character = &self.party[member];
+        if character.is_dead() {
+            return vec![];
+        }
+        let front = self.formation.is_front(member);
+        let ready: Vec<usize> = character
+            .passives()
+            .iter()
+            .enumerate()
+            .filter(|(_, passive)| {
+                answers(passive.trigger())
+                    && passive.is_ready(character, front, Some(self.enemies[target].target()))
+            })
+            .map(|(index, _)| index)
+            .collect();
+        let mut events = vec![];
+        for index in ready {
+            let passive = &mut self.party[member].passives_mut()[index];
+            passive.fire();
+            let skill = passive.skill().clone();
+            let (struck, passive) = self.execute(member, &skill, target, member);
+            events.push(Event::Passive {
+                actor: self.party[member].name().to_owned(),
+                skill: skill.name().to_owned(),
+                hits: struck.into_iter().flat_map(|(_, hits)| hits).collect(),
+            });
+            events.extend(passive);
+        }
+        events
+    }
+
+    fn fire_all(&mut self, enemy: usize, answers: impl Fn(Trigger) -> bool + Copy) -> Vec<Event> {
+        (0..self.party.len())
+            .flat_map(|member| self.fire(member, enemy, answers))
+            .collect()
+    }
+
+    fn hp_rates(&self) -> Vec<f32> {
+        self.party
+            .iter()
+            .map(|member| member.vitals().hp_rate())
+            .collect()
+    }
+
+    // HP thresholds crossed since the rates were taken, however the HP was lost
+    fn fire_crossed(&mut self, before: &[f32], enemy: usize) -> Vec<Event> {
+        (0..self.party.len())
+            .flat_map(|member| {
+                let (before, after) = (before[member], self.party[member].vitals().hp_rate());
+                self.fire(member, enemy, |trigger| trigger.crossed(before, after))
+            })
+            .collect()
+    }
+
+    // runs the skill without paying for it, the hits on each enemy come back apart
+    // from the other events
+    fn execute(
+        &mut self,
+        actor: usize,
+        skill: &Skill,
+        target: usize,
+        ally: usize,
+    ) -> (Vec<(usize, Vec<Hit>)>, Vec<Event>) {
+        let records = {
+            let mut targets = Targets::new(&mut self.party, &mut self.enemies);
+            targets.set_front(self.formation.front());
+            targets.set_enemy(target);
+            targets.set_ally(Some(ally));
+            targets.set_calculator(self.calculator.clone());
+            targets.set_critical(self.critical.clone());
+            targets.set_rng(self.rng.as_mut());
+            targets.set_battlefield(Some(&mut self.battlefield));
+            // actors come checked from validate
+            targets.execute(actor, skill).unwrap_or_default()
+        };
+        let mut hits = vec![];
+        let mut events = vec![];
+        for record in records {
+            for outcome in record.outcomes {
+                match outcome {
+                    Outcome::Damage {
+                        target,
+                        enemy,
+                        hits: dealt,
+                    } => {
+                        if dealt.iter().any(|hit| hit.broke) {
+                            events.push(Event::Break { target });
+                        }
+                        hits.push((enemy, dealt))
+                    }
+                    outcome => events.extend(Event::from_outcome(&record.effect, outcome)),
+                }
+            }
+        }
+        let count = hits.iter().map(|(_, dealt)| dealt.len()).sum();
+        self.overdrive.fill(count, skill.od_rate());
+        (hits, events)
+    }
+
+    fn round(&mut self, actions: Vec<Action>) -> Vec<Event> {
+        let mut events = vec![];
+        for action in actions {
+            if self.is_over() {
+                break;
+            }
+            let enemy = match action {
+                Action::Skill { target, .. } => target,
+                _ => 0,
+            };
+            let before = self.hp_rates();
+            events.extend(self.perform(action));
+            events.extend(self.fire_crossed(&before, enemy));
+        }
+        events
+    }
+
+    // a knocked out slot passes the action on to the first member standing,
+    // random picks the first one too when nothing is rolled
+    fn choose(&mut self, targeting: Targeting) -> Vec<usize> {
+        let standing = self.standing();
+        if standing.is_empty() {
+            return vec![];
+        }
+        match targeting {
+            Targeting::Slot(slot) => match self.formation.member(slot) {
+                Some(member) if slot < FRONT_SIZE && standing.contains(&member) => vec![member],
+                _ => vec![standing[0]],
+            },
+            Targeting::Random => match self.rng.as_mut() {
+                Some(rng) => vec![standing[rng.range(0, standing.len() as u32 - 1) as usize]],
+                None => vec![standing[0]],
+            },
+            Targeting::LowestHp => standing
+                .into_iter()
+                .min_by(|&a, &b| {
+                    let hp = |index: usize| self.party[index].vitals().hp();
+                    hp(a).total_cmp(&hp(b))
+                })
+                .into_iter()
+                .collect(),
+            Targeting::All => standing,
+        }
+    }
+
+    // damage goes through damage_from_enemy, buffs reach the chosen members or the enemy itself
+    fn enemy_act(&mut self, index: usize, action: EnemyAction) -> Vec<Event> {
+        let chosen = self.choose(action.targeting);
+        let standing = self.standing();
+        let enemy = &self.enemies[index];
+        let (Some(attack), Some(skill)) = (enemy.attack(), enemy.skills().get(action.skill)) else {
+            return vec![];
+        };
+        let mut events = vec![];
+        let mut own = vec![];
+        for effect in skill.effects() {
+            if let EffectKind::Zone(layer) = effect.kind() {
+                let rate = effect.effect_oneside(attack);
+                let outcome = Targets::lay_zone(&mut self.battlefield, effect, layer, rate);
+                events.extend(Event::from_outcome(effect.name(), outcome));
+                continue;
+            }
+            let amount = effect.effect_oneside(attack);
+            let buff = |value: f32| {
+                Buff::new(
+                    effect.name(),
+                    BuffCategory::SkillBuff,
+                    value,
+                    effect.duration(),
+                )
+            };
+            // the party is the enemy's enemy, its own side is the enemy alone
+            let members = match effect.scope() {
+                Scope::AllEnemies => standing.clone(),
+                Scope::Enemy => chosen.clone(),
+                Scope::Actor | Scope::Ally | Scope::AllAllies => {
+                    if let EffectKind::Buff(stat) = effect.kind() {
+                        own.push((stat, buff(amount)));
+                        events.push(Event::Buff {
+                            target: enemy.target().name().to_owned(),
+                            effect: effect.name().to_owned(),
+                            amount,
+                            duration: effect.duration(),
+                        });
+                    }
+                    continue;
+                }
+            };
+            // nobody left standing to land on
+            if members.is_empty() {
+                continue;
+            }
+            for slot in members {
+                let member = &mut self.party[slot];
+                match effect.kind() {
+                    EffectKind::Damage => {
+                        let mut calculator = self.calculator.clone();
+                        let mut critical = self.critical.clone();
+                        enemy.buffs().modify_attack(
+                            effect.element(),
+                            &mut calculator,
+                            &mut critical,
+                        );
+                        member
+                            .buffs()
+                            .modify_defense(effect.element(), &mut calculator);
+                        calculator.add_field(self.battlefield.rate(effect.element()));
+                        let luck = enemy.luck().map_or(0, |luck| luck.value());
+                        let rate = critical.rate_from(luck - member.luck().value());
+                        let count = effect.roll_hits(self.rng.as_mut());
+                        let rng = &mut self.rng;
+                        let hits = member.take(effect, attack, count, || {
+                            let mut calculator = calculator.clone();
+                            calculator.set_roll(rng.as_mut().map(Rng::next_f32));
+                            let rolled = rng.as_mut().map(Rng::next_f32);
+                            let hit = critical.apply(&mut calculator, rate, rolled);
+                            (calculator, hit)
+                        });
+                        events.push(Event::EnemySkill {
+                            actor: enemy.target().name().to_owned(),
+                            skill: skill.name().to_owned(),
+                            target: member.name().to_owned(),
+                            hits,
+                        });
+                        if member.is_dead() {
+                            events.push(Event::Knockout {
+                                target: member.name().to_owned(),
+                            });
+                        }
+                        continue;
+                    }
+                    EffectKind::Buff(stat) => member.buffs_mut().apply(stat, buff(amount)),
+                    EffectKind::AbilityUp(ability) => member
+                        .modifier(ability)
+                        .get_mut()
+                        .apply(buff(amount.round())),
+                    EffectKind::AbilityDown(ability) => member
+                        .modifier(ability)
+                        .get_mut()
+                        .apply(buff(-amount.round())),
+                    _ => continue,
+                }
+                events.push(Event::Buff {
+                    target: member.name().to_owned(),
+                    effect: effect.name().to_owned(),
+                    amount,
+                    duration: effect.duration(),
+                });
+            }
+        }
+        for (stat, buff) in own {
+            self.enemies[index].buffs_mut().apply(stat, buff);
+        }
+        events
+    }
+
+    fn enemy_turn(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+        for index in 0..self.enemies.len() {
+            if self.is_over() {
+                break;
+            }
+            let target = self.enemies[index].target();
+            if target.is_dead() {
+                continue;
+            }
+            let hp_rate = target.hp_rate();
+            let actions = self.enemies[index]
+                .pattern_mut()
+                .actions(self.turn, hp_rate);
+            for action in actions {
+                let before = self.hp_rates();
+                events.extend(self.enemy_act(index, action));
+                events.extend(self.fire_crossed(&before, index));
+                for member in 0..self.party.len() {
+                    events.extend(self.step_up(member));
+                }
+            }
+        }
+        events
+    }
+
+    pub fn step(&mut self, rotation: &mut dyn Rotation) -> Result<TurnLog, BattleError> {
+        let actions = rotation.actions(self.turn + 1);
+        self.validate(&actions)?;
+        let mut events = vec![];
+        if self.turn == 0 {
+            events.extend(self.fire_all(0, |trigger| trigger == Trigger::BattleStart));
+        }
+        self.turn += 1;
+        events.extend(self.fire_all(0, |trigger| trigger == Trigger::TurnStart));
+        events.extend(self.round(actions));
+        // overdrive turns stop the clock, nothing ticks and the enemies wait
+        let mut extra = 0;
+        while !self.is_over() && self.overdrive.next_turn() {
+            extra += 1;
+            let actions = rotation.overdrive_actions(self.turn, extra);
+            self.validate(&actions)?;
+            events.push(Event::OverdriveTurn { extra });
+            events.extend(self.round(actions));
+        }
+        events.extend(self.enemy_turn());
+        for member in self.party.iter_mut() {
+            for (pool, amount) in member.regenerate() {
+                events.push(Event::Heal {
+                    target: member.name().to_owned(),
+                    pool,
+                    amount,
+                    current: member.vitals().current(pool),
+                });
+            }
+            member.tick();
+            member.sp_mut().regenerate();
+        }
+        for zone in self.battlefield.tick() {
+            events.push(Event::ZoneFaded {
+                zone: zone.name().to_owned(),
+            });
+        }
+        for enemy in self.enemies.iter_mut() {
+            if enemy.tick() {
+                events.push(Event::DpRestored {
+                    target: enemy.target().name().to_owned(),
+                    dp: enemy.target().dp(),
+                });
+            }
+        }
+        Ok(TurnLog {
+            turn: self.turn,
+            events,
+        })
+    }
+
+    pub fn run(
+        &mut self,
+        rotation: &mut dyn Rotation,
+        max_turns: u32,
+    ) -> Result<BattleLog, BattleError> {
+        let mut log = BattleLog::default();
+        while self.turn < max_turns && !self.is_over() {
+            log.turns.push(self.step(rotation)?);
+            if self.is_won() {
+                log.kill_turn = Some(self.turn);
+            } else if self.is_lost() {
+                log.wipe_turn = Some(self.turn);
+            }
+        }
+        Ok(log)
+    }
+}
+
+impl Event {
+    // the event for what an effect did, damage is left to the caller with its hits
+    fn from_outcome(effect: &str, outcome: Outcome) -> Option<Self> {
+        match outcome {
+            Outcome::Damage { .. } | Outcome::SpCostDown { .. } => None,
+            Outcome::Sp { target, amount, sp } => Some(Self::Sp { target, amount, sp }),
+            Outcome::Buff {
+                target,
+                amount,
+                duration,
+                ..
+            } => Some(Self::Buff {
+                target,
+                effect: effect.to_owned(),
+                amount,
+                duration,
+            }),
+            Outcome::Heal {
+                target,
+                pool,
+                restored,
+                current,
+                ..
+            } => Some(Self::Heal {
+                target,
+                pool,
+                amount: restored,
+                current,
+            }),
+            Outcome::Zone {
+                layer,
+                rate,
+                duration,
+                replaced,
+            } => Some(Self::Zone {
+                zone: effect.to_owned(),
+                layer,
+                rate,
+                duration,
+                replaced,
+            }),
+        }
+    }
+}
+
+impl BattleLog {
+    // everything dealt to DP and HP over the battle
+    pub fn damage(&self) -> f32 {
+        self.turns
+            .iter()
+            .flat_map(|turn| turn.events.iter())
+            .map(|event| match event {
+                Event::Skill { hits, .. } | Event::Passive { hits, .. } => {
+                    hits.iter().map(|hit| hit.dp_damage + hit.hp_damage).sum()
+                }
+                _ => 0.0,
+            })
+            .sum()
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Skill {
+                actor,
+                skill,
+                cost,
+                target,
+                hits,
+            } => {
+                write!(f, "{} uses {} (SP {})", actor, skill, cost)?;
+                if let Some(target) = target {
+                    write!(f, " on {}", target)?;
+                }
+                write!(
+                    f,
+                    ": {}",
+                    hits.iter()
+                        .map(|hit| hit.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            }
+            Self::Sp { target, amount, sp } => {
+                write!(f, "{} gains {} SP ({})", target, amount, sp)
+            }
+            Self::Refused {
+                actor,
+                skill,
+                cost,
+                sp,
+            } => write!(f, "{} cannot afford {} (SP {}/{})", actor, skill, sp, cost),
+            Self::Buff {
+                target,
+                effect,
+                amount,
+                duration,
+            } => {
+                write!(f, "{} gets {} {}", target, effect, amount)?;
+                match duration {
+                    Some(duration) => write!(f, " for {} turns", duration),
+                    None => Ok(()),
+                }
+            }
+            Self::EnemySkill {
+                actor,
+                skill,
+                target,
+                hits,
+            } => write!(
+                f,
+                "{} uses {} on {}: {}",
+                actor,
+                skill,
+                target,
+                hits.iter()
+                    .map(|hit| hit.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::Knockout { target } => write!(f, "{} is knocked out", target),
+            Self::Break { target } => write!(f, "{} is broken", target),
+            Self::Overdrive { level, gauge, sp } => write!(
+                f,
+                "OD{} activated, SP +{} ({}% left)",
+                level,
+                sp,
+                gauge.round()
+            ),
+            Self::OverdriveRefused { level, gauge } => {
+                write!(f, "cannot activate OD{} at {}%", level, gauge.round())
+            }
+            Self::OverdriveTurn { extra } => write!(f, "overdrive turn {}", extra),
+            Self::Swap { out, into } => write!(f, "{} swaps out for {}", out, into),
+            Self::Zone {
+                zone,
+                rate,
+                duration,
+                replaced,
+                ..
+            } => {
+                write!(f, "{} is laid at +{}%", zone, (rate * 100.0).round())?;
+                if let Some(duration) = duration {
+                    write!(f, " for {} turns", duration)?;
+                }
+                match replaced {
+                    Some(replaced) => write!(f, ", replacing {}", replaced),
+                    None => Ok(()),
+                }
+            }
+            Self::ZoneFaded { zone } => write!(f, "{} fades", zone),
+            Self::Passive { actor, skill, hits } => {
+                write!(f, "{} triggers {}", actor, skill)?;
+                if !hits.is_empty() {
+                    write!(
+                        f,
+                        ": {}",
+                        hits.iter()
+                            .map(|hit| hit.to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )?;
+                }
+                Ok(())
+            }
+            Self::DpRestored { target, dp } => {
+                write!(f, "{} recovers from the break ({} DP)", target, dp.round())
+            }
+            Self::Heal {
+                target,
+                pool,
+                amount,
+                current,
+            } => write!(
+                f,
+                "{} recovers {} {} ({})",
+                target,
+                amount.round(),
+                pool,
+                current.round()
+            ),
+        }
+    }
+}
+
+impl Display for TurnLog {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "Turn {}", self.turn)?;
+        for event in self.events.iter() {
+            writeln!(f, "\t{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for BattleLog {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for turn in self.turns.iter() {
+            write!(f, "{}", turn)?;
+        }
+        match (self.kill_turn, self.wipe_turn) {
+            (Some(turn), _) => writeln!(f, "Killed on turn {}", turn),
+            (None, Some(turn)) => writeln!(f, "Wiped out on turn {}", turn),
+            (None, None) => writeln!(f, "Not killed after {} turns", self.turns.len()),
+        }
+    }
+}
+
+impl Display for BattleError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::EmptyParty => write!(f, "the party is empty"),
+            Self::PartyTooLarge(size) => {
+                write!(
+                    f,
+                    "{} members exceed the party size of {}",
+                    size, PARTY_SIZE
+                )
+            }
+            Self::NoEnemy => write!(f, "there is no enemy"),
+            Self::NotInFront(actor) => write!(f, "member {} is not in the front row", actor),
+            Self::AlreadyActed(actor) => write!(f, "member {} already acted this turn", actor),
+            Self::NoSkill { actor, skill } => {
+                write!(f, "member {} has no skill {}", actor, skill)
+            }
+            Self::NoTarget(target) => write!(f, "there is no enemy {}", target),
+            Self::NoAlly(ally) => write!(f, "there is no member {}", ally),
+            Self::InvalidSwap { out, into } => write!(
+                f,
+                "member {} cannot swap out for member {} from the back row",
+                out, into
+            ),
+            Self::Unplaced(member) => write!(f, "member {} has no slot", member),
+            Self::NotInParty(member) => write!(f, "member {} is not in the party", member),
+            Self::NoVitals(member) => {
+                write!(f, "member {} has no HP to take enemy attacks", member)
+            }
+            Self::InvalidOverdrive(level) => {
+                write!(
+                    f,
+                    "there is no OD{}, levels go from 1 to {}",
+                    level, OD_LEVELS
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for BattleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::*;
+    use crate::buff::BuffStat;
+    use crate::element::Element;
+    use crate::field::Zone;
+    use crate::passive::{Condition, Passive};
+    use crate::pattern::Pattern;
+    use crate::skill::SkillEffect;
+    use crate::testing::{enemy, member, single};
+
+    #[test]
+    fn kill_turn() {
+        let mut battle = Battle::new(
+            vec![member("a", 200), member("b", 150)],
+            vec![enemy(5000.0, 10000.0)],
+        )
+        .expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0), Action::skill(1, 0, 0)]);
+        let log = battle.run(&mut rotation, 10).expect("it should succeed");
+        // 5000 + 3000 per turn against 15000 DP and HP, the first hit breaks the DP
+        assert_eq!(log.kill_turn, Some(2));
+        assert_eq!(log.turns[0].events.len(), 3);
+        assert!(matches!(log.turns[0].events[1], Event::Break { .. }));
+        assert!(battle.is_over());
+    }
+
+    #[test]
+    fn invalid_actions() {
+        let party = (0..4).map(|i| member(&i.to_string(), 100)).collect();
+        let mut battle = Battle::new(party, vec![enemy(0.0, 100.0)]).expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(3, 0, 0)]);
+        assert_eq!(battle.step(&mut rotation), Err(BattleError::NotInFront(3)));
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0), Action::skill(0, 0, 0)]);
+        assert_eq!(
+            battle.step(&mut rotation),
+            Err(BattleError::AlreadyActed(0))
+        );
+    }
+
+    #[test]
+    fn sp_economy() {
+        let mut attacker = member("a", 200);
+        let mut skill = Skill::new("expensive");
+        skill.set_sp_cost(8);
+        skill.add_effect(SkillEffect::new_damage("expensive", 1000.0, 100));
+        attacker.add_skill(skill);
+        let mut support = member("b", 100);
+        let mut skill = Skill::new("sp up");
+        skill.set_sp_cost(2);
+        let mut effect = SkillEffect::new_sp("sp up", 4);
+        effect.set_scope(Scope::Ally);
+        skill.add_effect(effect);
+        support.add_skill(skill);
+        let mut battle = Battle::new(vec![attacker, support], vec![enemy(0.0, 100000.0)])
+            .expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 1, 0), Action::support(1, 1, 0)]);
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!(matches!(
+            log.events[0],
+            Event::Refused { cost: 8, sp: 4, .. }
+        ));
+        assert!(matches!(log.events[1], Event::Skill { target: None, .. }));
+        assert!(matches!(
+            log.events[2],
+            Event::Sp {
+                amount: 4,
+                sp: 8,
+                ..
+            }
+        ));
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!(matches!(
+            &log.events[0],
+            Event::Skill {
+                cost: 8,
+                target: Some(_),
+                ..
+            }
+        ));
+        // 10 - 8 paid + 4 from the ally + 2 regen
+        assert_eq!(battle.party()[0].sp().current(), 8);
+    }
+
+    #[test]
+    fn overdrive_turns() {
+        let mut attacker = member("a", 200);
+        let mut skill = Skill::new("combo");
+        skill.set_od_rate(2.0);
+        let mut effect = SkillEffect::new_damage("combo", 1000.0, 100);
+        effect.set_hits(2);
+        skill.add_effect(effect);
+        attacker.add_skill(skill);
+        let mut battle =
+            Battle::new(vec![attacker], vec![enemy(0.0, 100000.0)]).expect("it should succeed");
+        battle.set_overdrive(Overdrive::new(90.0));
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 1, 0), Action::Overdrive { level: 1 }]);
+        rotation.add_overdrive_turn(vec![Action::skill(0, 0, 0)]);
+        rotation.add_turn(vec![Action::Overdrive { level: 1 }]);
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        // two hits at 2.5% doubled fill the last 10%, the overdrive turn does not fill
+        assert!(matches!(
+            log.events[1],
+            Event::Overdrive {
+                level: 1,
+                sp: 5,
+                ..
+            }
+        ));
+        assert!(matches!(log.events[2], Event::OverdriveTurn { extra: 1 }));
+        assert!(matches!(log.events[3], Event::Skill { .. }));
+        assert_eq!(battle.overdrive().gauge(), 0.0);
+        assert_eq!(battle.turn(), 1);
+        assert_eq!(battle.party()[0].sp().current(), 11);
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!(matches!(log.events[0], Event::OverdriveRefused { .. }));
+        rotation.add_turn(vec![Action::Overdrive { level: 4 }]);
+        assert_eq!(
+            battle.step(&mut rotation),
+            Err(BattleError::InvalidOverdrive(4))
+        );
+    }
+
+    #[test]
+    fn formation_swaps() {
+        let mut cheer = Skill::new("cheer");
+        let mut attack_up =
+            SkillEffect::new_buff("cheer", EffectKind::Buff(BuffStat::AttackUp), 0.5, 0.5, 0);
+        attack_up.set_scope(Scope::AllAllies);
+        attack_up.set_duration(Some(3));
+        cheer.add_effect(attack_up);
+        let mut party: Vec<Character> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|name| member(name, 100))
+            .collect();
+        party[3].add_swap_passive(cheer);
+        let mut battle = Battle::new(party, vec![enemy(0.0, 100000.0)]).expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![
+            Action::skill(0, 0, 0),
+            Action::Swap { out: 0, into: 3 },
+        ]);
+        rotation.add_turn(vec![Action::Swap { out: 3, into: 0 }]);
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!(matches!(log.events[2], Event::Passive { .. }));
+        assert_eq!(battle.formation().front(), vec![3, 1, 2]);
+        // the buff reached the front d joined and stays with d
+        let attack_up = |battle: &Battle, member: usize| {
+            battle.party()[member].buffs().total(BuffStat::AttackUp)
+        };
+        assert_eq!(attack_up(&battle, 0), 0.0);
+        assert_eq!(attack_up(&battle, 1), 0.5);
+        battle.step(&mut rotation).expect("it should succeed");
+        assert_eq!(battle.formation().back(), vec![3]);
+        assert_eq!(attack_up(&battle, 3), 0.5);
+
+        // d takes the slot b already acted from
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![
+            Action::skill(1, 0, 0),
+            Action::Swap { out: 1, into: 3 },
+            Action::skill(3, 0, 0),
+        ]);
+        assert_eq!(
+            battle.step(&mut rotation),
+            Err(BattleError::AlreadyActed(3))
+        );
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::Swap { out: 3, into: 1 }]);
+        assert_eq!(
+            battle.step(&mut rotation),
+            Err(BattleError::InvalidSwap { out: 3, into: 1 })
+        );
+
+        // a swap that costs the action leaves nothing for the member coming in
+        battle.set_swap_cost(SwapCost::Action);
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![
+            Action::Swap { out: 1, into: 3 },
+            Action::skill(3, 0, 0),
+        ]);
+        assert_eq!(
+            battle.step(&mut rotation),
+            Err(BattleError::AlreadyActed(3))
+        );
+        let formation = Formation::from_slots([Some(0), Some(1), Some(2), Some(3), Some(7), None])
+            .expect("it should succeed");
+        assert_eq!(
+            battle.set_formation(formation),
+            Err(BattleError::NotInParty(7))
+        );
+    }
+
+    #[test]
+    fn passive_triggers() {
+        let sp_up = |name: &str, trigger: Trigger, amount: i32| {
+            let mut skill = Skill::new(name);
+            skill.add_effect(SkillEffect::new_sp(name, amount));
+            Passive::new(trigger, skill)
+        };
+        let mut attacker = member("a", 200);
+        attacker.add_passive(sp_up("opening", Trigger::BattleStart, 3));
+        attacker.add_passive(sp_up("focus", Trigger::Critical, 1));
+        let mut rich = sp_up("rich", Trigger::TurnStart, 5);
+        rich.add_condition(Condition::SpAtLeast(50));
+        attacker.add_passive(rich);
+        let mut breaker = member("b", 100);
+        breaker.add_passive(sp_up("follow up", Trigger::Break, 2));
+        let mut battle = Battle::new(vec![attacker, breaker], vec![enemy(1000.0, 100000.0)])
+            .expect("it should succeed");
+        let mut critical = Critical::new();
+        critical.set_base_rate(1.0, 0.0);
+        battle.set_critical(critical);
+        battle.set_rng(Some(Rng::new(7)));
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0)]);
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!(matches!(log.events[0], Event::Passive { .. }));
+        // 4 + 3 at the start + 1 on the critical + 2 regen, b gets 2 when the DP breaks
+        assert_eq!(battle.party()[0].sp().current(), 10);
+        assert_eq!(battle.party()[1].sp().current(), 8);
+        battle.step(&mut rotation).expect("it should succeed");
+        assert_eq!(battle.party()[0].passives()[0].fired(), 1);
+        assert_eq!(battle.party()[0].passives()[2].fired(), 0);
+
+        // the follow up lands on the enemy that broke, not the first one
+        let mut chaser = member("c", 100);
+        let mut follow_up = Skill::new("follow up");
+        follow_up.add_effect(SkillEffect::new_damage("follow up", 1000.0, 100));
+        chaser.add_passive(Passive::new(Trigger::Break, follow_up));
+        let mut battle = Battle::new(
+            vec![member("a", 200), chaser],
+            vec![enemy(0.0, 100000.0), enemy(1000.0, 100000.0)],
+        )
+        .expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 1)]);
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!(log.events.iter().any(|event| matches!(
+            event,
+            Event::Passive { hits, .. } if !hits.is_empty()
+        )));
+        assert_eq!(battle.enemies()[0].target().hp(), 100000.0);
+    }
+
+    #[test]
+    fn field_zones() {
+        let mut attacker = member("a", 200);
+        let mut skill = Skill::new("fire slash");
+        let mut effect = SkillEffect::new_damage("fire slash", 1000.0, 100);
+        effect.set_element(Element::Fire);
+        skill.add_effect(effect);
+        attacker.add_skill(skill);
+        let mut support = member("b", 100);
+        let mut skill = Skill::new("ice field");
+        let mut effect = SkillEffect::new_zone("ice field", Layer::Field, 0.3, 0.3, 0);
+        effect.set_element(Element::Ice);
+        effect.set_duration(Some(1));
+        skill.add_effect(effect);
+        support.add_skill(skill);
+        let mut battle = Battle::new(vec![attacker, support], vec![enemy(0.0, 100000.0)])
+            .expect("it should succeed");
+        let mut battlefield = Battlefield::new();
+        battlefield.lay(
+            Layer::Field,
+            Zone::new("fire field", Some(Element::Fire), 0.5, Some(3)),
+        );
+        battle.set_battlefield(battlefield);
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 1, 0), Action::skill(1, 1, 0)]);
+        rotation.add_turn(vec![Action::skill(0, 1, 0)]);
+        let damage = |event: &Event| match event {
+            Event::Skill { hits, .. } => hits[0].hp_damage,
+            _ => 0.0,
+        };
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        let fire = damage(&log.events[0]);
+        assert!(matches!(
+            &log.events[2],
+            Event::Zone { replaced: Some(replaced), .. } if replaced == "fire field"
+        ));
+        assert!(matches!(log.events[3], Event::ZoneFaded { .. }));
+        let log = battle.step(&mut rotation).expect("it should succeed");
+        assert!((fire / damage(&log.events[0]) - 1.5).abs() < 1e-4);
+
+        // a field rate set on the calculator stays under the battlefield
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0)]);
+        let dealt = |field: f32| {
+            let mut battle = Battle::new(vec![member("a", 200)], vec![enemy(0.0, 100000.0)])
+                .expect("it should succeed");
+            let mut calculator = DamageCalculator::new();
+            calculator.set_field(field);
+            battle.set_calculator(calculator);
+            let log = battle
+                .step(&mut rotation.clone())
+                .expect("it should succeed");
+            damage(&log.events[0])
+        };
+        assert!((dealt(0.5) / dealt(0.0) - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn enemy_attacks() {
+        let mut attacker = member("a", 200);
+        attacker.set_vitals(400.0, 1000.0);
+        let mut rally = Skill::new("rally");
+        rally.add_effect(SkillEffect::new_sp("rally", 2));
+        attacker.add_passive(Passive::new(Trigger::HpBelow(0.5), rally));
+        let mut boss = enemy(0.0, 100000.0);
+        boss.set_attack(single(Ability::Strength(100)));
+        boss.add_skill({
+            let mut skill = Skill::new("claw");
+            skill.add_effect(SkillEffect::new_damage("claw", 100.0, 100));
+            skill
+        });
+        boss.set_pattern(Pattern::new(vec![vec![EnemyAction::new(
+            0,
+            Targeting::Random,
+        )]]));
+        assert!(matches!(
+            Battle::new(vec![attacker.clone(), member("b", 200)], vec![boss.clone()]),
+            Err(BattleError::NoVitals(1))
+        ));
+        let mut reserve = member("b", 200);
+        reserve.set_vitals(400.0, 1000.0);
+        let mut relay = Battle::new(vec![attacker.clone(), reserve], vec![boss.clone()])
+            .expect("it should succeed");
+        let mut battle = Battle::new(vec![attacker], vec![boss]).expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0)]);
+        // enemies crit through the battle's critical settings too
+        let mut lucky = battle.clone();
+        let mut critical = Critical::new();
+        critical.set_base_rate(1.0, 0.0);
+        lucky.set_critical(critical);
+        lucky.set_rng(Some(Rng::new(1)));
+        let log = lucky.step(&mut rotation).expect("it should succeed");
+        assert!(matches!(
+            &log.events[1],
+            Event::EnemySkill { hits, .. } if hits[0].critical
+        ));
+        let log = battle.run(&mut rotation, 10).expect("it should succeed");
+        // 500 a turn, the first breaks the 400 DP and a fifth carries over to HP
+        assert!(matches!(
+            &log.turns[0].events[1],
+            Event::EnemySkill { hits, .. } if hits[0].dp_damage == 400.0 && hits[0].hp_damage == 100.0
+        ));
+        assert_eq!(log.wipe_turn, Some(3));
+        // 900 then 400 HP, the second hit falls through half
+        assert!(log.turns[1].events.iter().any(|event| matches!(
+            event,
+            Event::Passive { skill, .. } if skill == "rally"
+        )));
+        assert_eq!(log.kill_turn, None);
+        assert!(matches!(
+            log.turns[2].events.last(),
+            Some(Event::Knockout { .. })
+        ));
+
+        // b moves up from the back row once a falls and lasts three more turns
+        relay
+            .set_formation(
+                Formation::from_slots([Some(0), None, None, Some(1), None, None])
+                    .expect("it should succeed"),
+            )
+            .expect("it should succeed");
+        let log = relay
+            .run(&mut ScriptedRotation::new(), 10)
+            .expect("it should succeed");
+        assert!(matches!(
+            log.turns[2].events.last(),
+            Some(Event::Swap { into, .. }) if into == "b"
+        ));
+        assert_eq!(log.wipe_turn, Some(6));
+    }
+
+    #[test]
+    fn enemy_buffs() {
+        let mut victim = member("a", 200);
+        victim.set_vitals(100.0, 100.0);
+        let mut boss = enemy(0.0, 100000.0);
+        boss.set_attack(single(Ability::Strength(100)));
+        let mut claw = Skill::new("claw");
+        claw.add_effect(SkillEffect::new_damage("claw", 100000.0, 100));
+        boss.add_skill(claw);
+        let mut howl = Skill::new("howl");
+        howl.add_effect(SkillEffect::new_buff(
+            "roar",
+            EffectKind::Buff(BuffStat::AttackUp),
+            0.2,
+            0.2,
+            100,
+        ));
+        howl.add_effect(SkillEffect::new_buff(
+            "curse",
+            EffectKind::Buff(BuffStat::DefenseDown),
+            0.2,
+            0.2,
+            100,
+        ));
+        boss.add_skill(howl);
+        let mut battle = Battle::new(vec![victim], vec![boss]).expect("it should succeed");
+        battle.enemy_act(0, EnemyAction::new(0, Targeting::Slot(0)));
+        assert!(battle.standing().is_empty());
+        // the curse finds nobody and stays off the enemy, the roar is its own
+        let events = battle.enemy_act(0, EnemyAction::new(1, Targeting::Slot(0)));
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            Event::Buff { target, effect, .. } if target == "enemy" && effect == "roar"
+        ));
+    }
+
+    #[test]
+    fn parallel_battles() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Battle>();
+        let battle = Battle::new(
+            vec![member("a", 200), member("b", 150)],
+            vec![enemy(5000.0, 10000.0)],
+        )
+        .expect("it should succeed");
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0), Action::skill(1, 0, 0)]);
+        let damage: Vec<f32> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (1..=4)
+                .map(|strength_up| {
+                    let mut battle = battle.clone();
+                    let mut rotation = rotation.clone();
+                    scope.spawn(move || {
+                        battle.party()[1]
+                            .modifier(AbilityName::Strength)
+                            .get_mut()
+                            .apply_positive(strength_up * 10);
+                        battle
+                            .run(&mut rotation, 1)
+                            .expect("it should succeed")
+                            .damage()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("it should join"))
+                .collect()
+        });
+        // 5000 from the first member and 3400 rising by 400 from the second
+        assert_eq!(damage, vec![8400.0, 8800.0, 9200.0, 9600.0]);
+        // the clones never touched the original
+        assert_eq!(battle.party()[1].attack().value(), 150);
+        assert_eq!(battle.enemies()[0].target().hp(), 10000.0);
+    }
+}
@@ -0,0 +1,298 @@
+use crate::critical::Critical;
+use crate::element::Element;
+use crate::skill::{DamageCalculator, Pool};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BuffCategory {
+    SkillBuff,
+    Passive,
+    Field,
+    Accessory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stacking {
+    Highest,
+    Top(usize),
+    Additive,
+}
+
+// rates feeding the damage pipeline rather than an ability, downs are kept positive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuffStat {
+    AttackUp,
+    ElementAttackUp(Element),
+    CriticalRateUp,
+    CriticalDamageUp,
+    DefenseDown,
+    ElementDefenseDown(Element),
+    // restored at the end of every turn
+    Regen(Pool),
+}
+
+#[derive(Debug, Clone)]
+pub struct Buff {
+    source: String,
+    category: BuffCategory,
+    value: f32,
+    duration: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuffLedger {
+    entries: Vec<Buff>,
+    stacking: HashMap<BuffCategory, Stacking>,
+}
+
+// a ledger for each stat
+#[derive(Debug, Clone, Default)]
+pub struct StatBuffs {
+    ledgers: HashMap<BuffStat, BuffLedger>,
+}
+
+impl Buff {
+    // duration in turns, None never expires
+    pub fn new(source: &str, category: BuffCategory, value: f32, duration: Option<u32>) -> Self {
+        Self {
+            source: source.to_owned(),
+            category,
+            value,
+            duration,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn category(&self) -> BuffCategory {
+        self.category
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn duration(&self) -> Option<u32> {
+        self.duration
+    }
+}
+
+impl Default for BuffLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuffLedger {
+    pub fn new() -> Self {
+        Self {
+            entries: vec![],
+            stacking: HashMap::from([
+                (BuffCategory::SkillBuff, Stacking::Top(2)),
+                (BuffCategory::Passive, Stacking::Additive),
+                (BuffCategory::Field, Stacking::Highest),
+                (BuffCategory::Accessory, Stacking::Additive),
+            ]),
+        }
+    }
+
+    pub fn stacking(&self, category: BuffCategory) -> Stacking {
+        self.stacking
+            .get(&category)
+            .copied()
+            .unwrap_or(Stacking::Additive)
+    }
+
+    pub fn set_stacking(&mut self, category: BuffCategory, stacking: Stacking) {
+        self.stacking.insert(category, stacking);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Buff> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn apply(&mut self, buff: Buff) {
+        self.entries.push(buff);
+    }
+
+    pub fn remove(&mut self, source: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|buff| buff.source != source);
+        len != self.entries.len()
+    }
+
+    // counts one turn down and drops what ran out, returning how many expired
+    pub fn tick(&mut self) -> usize {
+        let len = self.entries.len();
+        for buff in self.entries.iter_mut() {
+            if let Some(duration) = buff.duration.as_mut() {
+                *duration = duration.saturating_sub(1);
+            }
+        }
+        self.entries.retain(|buff| buff.duration != Some(0));
+        len - self.entries.len()
+    }
+
+    // summed in category order so the total does not depend on hashing
+    fn stacked(&self, positive: bool) -> f32 {
+        let mut by_category: BTreeMap<BuffCategory, Vec<f32>> = BTreeMap::new();
+        for buff in self.entries.iter() {
+            if (buff.value > 0.0) == positive && buff.value != 0.0 {
+                by_category
+                    .entry(buff.category)
+                    .or_default()
+                    .push(buff.value.abs());
+            }
+        }
+        let total: f32 = by_category
+            .into_iter()
+            .map(|(category, mut values)| {
+                values.sort_by(|a, b| b.total_cmp(a));
+                match self.stacking(category) {
+                    Stacking::Highest => values.first().copied().unwrap_or(0.0),
+                    Stacking::Top(n) => values.iter().take(n).sum(),
+                    Stacking::Additive => values.iter().sum(),
+                }
+            })
+            .sum();
+        if positive {
+            total
+        } else {
+            -total
+        }
+    }
+
+    pub fn positive(&self) -> f32 {
+        self.stacked(true)
+    }
+
+    pub fn negative(&self) -> f32 {
+        self.stacked(false)
+    }
+
+    pub fn total(&self) -> f32 {
+        self.positive() + self.negative()
+    }
+}
+
+impl StatBuffs {
+    pub fn new() -> Self {
+        Self {
+            ledgers: HashMap::new(),
+        }
+    }
+
+    pub fn ledger(&self, stat: BuffStat) -> Option<&BuffLedger> {
+        self.ledgers.get(&stat)
+    }
+
+    pub fn apply(&mut self, stat: BuffStat, buff: Buff) {
+        self.ledgers.entry(stat).or_default().apply(buff);
+    }
+
+    pub fn remove(&mut self, source: &str) -> bool {
+        let mut removed = false;
+        for ledger in self.ledgers.values_mut() {
+            removed |= ledger.remove(source);
+        }
+        removed
+    }
+
+    pub fn tick(&mut self) -> usize {
+        self.ledgers.values_mut().map(BuffLedger::tick).sum()
+    }
+
+    pub fn total(&self, stat: BuffStat) -> f32 {
+        self.ledgers.get(&stat).map_or(0.0, BuffLedger::total)
+    }
+
+    // the attacker's side of a hit with an effect of the element
+    pub fn modify_attack(
+        &self,
+        element: Element,
+        calculator: &mut DamageCalculator,
+        critical: &mut Critical,
+    ) {
+        calculator.add_attack_up(self.total(BuffStat::AttackUp));
+        calculator.add_attack_up(self.total(BuffStat::ElementAttackUp(element)));
+        critical.add_rate_up(self.total(BuffStat::CriticalRateUp));
+        critical.add_damage_up(self.total(BuffStat::CriticalDamageUp));
+    }
+
+    // the defender's side of a hit with an effect of the element
+    pub fn modify_defense(&self, element: Element, calculator: &mut DamageCalculator) {
+        calculator.add_defense_down(self.total(BuffStat::DefenseDown));
+        calculator.add_defense_down(self.total(BuffStat::ElementDefenseDown(element)));
+    }
+}
+
+impl Display for Buff {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}({:?}): {}", self.source, self.category, self.value)?;
+        match self.duration {
+            Some(duration) => write!(f, " for {}", duration),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacking_rules() {
+        let mut ledger = BuffLedger::new();
+        ledger.apply(Buff::new("a", BuffCategory::SkillBuff, 30.0, Some(2)));
+        ledger.apply(Buff::new("b", BuffCategory::SkillBuff, 50.0, Some(2)));
+        ledger.apply(Buff::new("c", BuffCategory::SkillBuff, 40.0, Some(2)));
+        ledger.apply(Buff::new("d", BuffCategory::Field, 10.0, None));
+        ledger.apply(Buff::new("e", BuffCategory::Field, 20.0, None));
+        ledger.apply(Buff::new("f", BuffCategory::Passive, 5.0, None));
+        ledger.apply(Buff::new("g", BuffCategory::Passive, 5.0, None));
+        ledger.apply(Buff::new("h", BuffCategory::SkillBuff, -25.0, Some(1)));
+        assert_eq!(ledger.positive(), 90.0 + 20.0 + 10.0);
+        assert_eq!(ledger.negative(), -25.0);
+        ledger.set_stacking(BuffCategory::SkillBuff, Stacking::Additive);
+        assert_eq!(ledger.positive(), 120.0 + 20.0 + 10.0);
+    }
+
+    #[test]
+    fn expiry_and_removal() {
+        let mut ledger = BuffLedger::new();
+        ledger.apply(Buff::new("a", BuffCategory::SkillBuff, 30.0, Some(1)));
+        ledger.apply(Buff::new("b", BuffCategory::SkillBuff, 50.0, Some(2)));
+        ledger.apply(Buff::new("c", BuffCategory::Accessory, 10.0, None));
+        assert_eq!(ledger.tick(), 1);
+        assert_eq!(ledger.total(), 60.0);
+        assert!(ledger.remove("c"));
+        assert!(!ledger.remove("c"));
+        assert_eq!(ledger.tick(), 1);
+        assert!(ledger.is_empty());
+
+        let mut buffs = StatBuffs::new();
+        buffs.apply(
+            BuffStat::AttackUp,
+            Buff::new("a", BuffCategory::SkillBuff, 0.3, Some(1)),
+        );
+        buffs.apply(
+            BuffStat::ElementAttackUp(Element::Fire),
+            Buff::new("a", BuffCategory::SkillBuff, 0.2, Some(2)),
+        );
+        assert_eq!(buffs.total(BuffStat::ElementAttackUp(Element::Ice)), 0.0);
+        assert_eq!(buffs.tick(), 1);
+        assert!(buffs.remove("a"));
+        assert_eq!(buffs.total(BuffStat::ElementAttackUp(Element::Fire)), 0.0);
+    }
+}
@@ -0,0 +1,1734 @@
+use crate::ability::{
+    Ability, AbilityModel, AbilityModelType, AbilityModifier, AbilityModifierHelper, AbilityName,
+    ModelFormula, Rounding,
+};
+use crate::battle::{Action, Battle, ScriptedRotation, SwapCost};
+use crate::buff::BuffStat;
+use crate::character::{Character, Formula};
+use crate::element::{AttackType, Element};
+use crate::equipment::{Accessory, Loadout, Weapon};
+use crate::field::Layer;
+use crate::growth::{level_skill, Growth, GrowthTable, SKILL_LEVEL_RATE};
+use crate::overdrive::{Overdrive, OD_LEVELS};
+use crate::passive::{Condition, Passive, Trigger};
+use crate::pattern::{EnemyAction, Pattern, Targeting, TurnCondition};
+use crate::skill::{BreakCondition, EffectKind, Pool, Scope, Skill, SkillEffect};
+use crate::sp::SpPool;
+use crate::target::{Enemy, Target};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelKind {
+    Single,
+    Equal,
+    WeightedOnPrior,
+    Weighted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKindSpec {
+    Damage,
+    Recover,
+    Sp,
+    SpCostDown,
+    AttackUp,
+    ElementAttackUp,
+    CriticalRateUp,
+    CriticalDamageUp,
+    DefenseDown,
+    ElementDefenseDown,
+    AbilityUp,
+    AbilityDown,
+    Regen,
+    Field,
+    Zone,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Catalogue {
+    #[serde(default)]
+    pub styles: Vec<StyleSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleSpec {
+    pub name: String,
+    // at level 1 when the style has growth
+    pub abilities: AbilitiesSpec,
+    #[serde(default)]
+    pub growth: Option<GrowthSpec>,
+    pub attack: ModelSpec,
+    #[serde(default)]
+    pub defense: Option<ModelSpec>,
+    #[serde(default)]
+    pub heal: Option<ModelSpec>,
+    #[serde(default)]
+    pub sp: Option<SpSpec>,
+    // DP and HP of the member, untracked when left out
+    #[serde(default)]
+    pub dp: Option<f32>,
+    #[serde(default)]
+    pub hp: Option<f32>,
+    #[serde(default)]
+    pub skills: Vec<SkillSpec>,
+    #[serde(default)]
+    pub passives: Vec<PassiveSpec>,
+    // used for free when the style is swapped into the front
+    #[serde(default)]
+    pub swap_passives: Vec<SkillSpec>,
+    // the loadout the member starts with
+    #[serde(default)]
+    pub weapon: Option<WeaponSpec>,
+    #[serde(default)]
+    pub accessories: Vec<AccessorySpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct GrowthSpec {
+    pub level: u32,
+    #[serde(default)]
+    pub limit_break: u32,
+    pub max_level: u32,
+    #[serde(default)]
+    pub max_limit_break: u32,
+    #[serde(default)]
+    pub per_level: HashMap<AbilityName, f32>,
+    #[serde(default)]
+    pub per_limit_break: HashMap<AbilityName, i32>,
+    // flat gains from stat-boost items
+    #[serde(default)]
+    pub enhancement: HashMap<AbilityName, i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct WeaponSpec {
+    pub name: String,
+    pub element: Element,
+    pub attack_type: AttackType,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessorySpec {
+    pub name: String,
+    #[serde(default)]
+    pub flat: HashMap<AbilityName, i32>,
+    // rates of the base ability
+    #[serde(default)]
+    pub percent: HashMap<AbilityName, f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PassiveSpec {
+    pub name: String,
+    pub trigger: Trigger,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    // times it can fire in a battle, no limit when left out
+    #[serde(default)]
+    pub limit: Option<u32>,
+    pub effects: Vec<EffectSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbilitiesSpec {
+    pub strength: i32,
+    pub dexterity: i32,
+    pub stamina: i32,
+    pub endurement: i32,
+    pub luck: i32,
+    pub intelligence: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelSpec {
+    pub kind: ModelKind,
+    pub abilities: Vec<AbilityName>,
+    // one weight per ability, only read by the weighted kind
+    #[serde(default)]
+    pub weights: Vec<u32>,
+    #[serde(default)]
+    pub rounding: Rounding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpSpec {
+    pub initial: i32,
+    pub cap: i32,
+    pub regen: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillSpec {
+    pub name: String,
+    #[serde(default)]
+    pub sp_cost: i32,
+    // scales the overdrive gauge each hit fills
+    #[serde(default)]
+    pub od_rate: Option<f32>,
+    // damage and recover grow by level_rate for every level over 1
+    #[serde(default)]
+    pub level: Option<u32>,
+    #[serde(default)]
+    pub level_rate: Option<f32>,
+    pub effects: Vec<EffectSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EffectSpec {
+    pub name: String,
+    #[serde(default = "EffectSpec::default_kind")]
+    pub kind: EffectKindSpec,
+    pub min: f32,
+    // defaults to 5x min for damage, 3x min for recover and min for everything else
+    #[serde(default)]
+    pub max: Option<f32>,
+    #[serde(default)]
+    pub border: i32,
+    #[serde(default)]
+    pub scope: Option<Scope>,
+    #[serde(default)]
+    pub element: Option<Element>,
+    #[serde(default)]
+    pub attack_type: Option<AttackType>,
+    #[serde(default)]
+    pub dp_rate: Option<f32>,
+    #[serde(default)]
+    pub hp_rate: Option<f32>,
+    #[serde(default)]
+    pub destruction: Option<f32>,
+    #[serde(default)]
+    pub duration: Option<u32>,
+    // a fixed count, or the least of a random count up to max_hits
+    #[serde(default)]
+    pub hits: Option<u32>,
+    #[serde(default)]
+    pub max_hits: Option<u32>,
+    #[serde(default)]
+    pub hit_weights: Vec<f32>,
+    // the ability raised or lowered by ability_up and ability_down
+    #[serde(default)]
+    pub ability: Option<AbilityName>,
+    // scales a support effect instead of the attack or heal model
+    #[serde(default)]
+    pub model: Option<ModelSpec>,
+    // the pool restored by recover and regen, DP when left out
+    #[serde(default)]
+    pub pool: Option<Pool>,
+    #[serde(default)]
+    pub overheal: Option<f32>,
+    // damage multiplier against an enemy that is already broken
+    #[serde(default)]
+    pub break_bonus: Option<f32>,
+    #[serde(default)]
+    pub condition: Option<BreakCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct BattleSpec {
+    // catalogue files relative to the battle file, resolved by BattleSpec::load
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub styles: Vec<StyleSpec>,
+    pub party: Vec<String>,
+    pub enemies: Vec<EnemySpec>,
+    #[serde(default)]
+    pub rotation: Vec<TurnSpec>,
+    #[serde(default = "BattleSpec::default_max_turns")]
+    pub max_turns: u32,
+    #[serde(default)]
+    pub overdrive: Option<OverdriveSpec>,
+    // whether a swap uses the action of the slot, free when left out
+    #[serde(default)]
+    pub swap_cost: SwapCost,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct OverdriveSpec {
+    // the gauge in percent at the start of the battle
+    #[serde(default)]
+    pub gauge: f32,
+    #[serde(default)]
+    pub hit_rate: Option<f32>,
+    #[serde(default)]
+    pub sp_boosts: Option<[i32; OD_LEVELS as usize]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemySpec {
+    pub name: String,
+    pub dp: f32,
+    pub hp: f32,
+    pub defense: i32,
+    #[serde(default)]
+    pub luck: i32,
+    #[serde(default)]
+    pub max_destruction: Option<f32>,
+    #[serde(default)]
+    pub elements: HashMap<Element, f32>,
+    #[serde(default)]
+    pub attack_types: HashMap<AttackType, f32>,
+    // Strength behind the enemy's skills, an enemy without it never acts
+    #[serde(default)]
+    pub attack: Option<i32>,
+    #[serde(default)]
+    pub skills: Vec<SkillSpec>,
+    // the first phase opens the battle, the others follow as HP falls
+    #[serde(default)]
+    pub phases: Vec<PhaseSpec>,
+    #[serde(default)]
+    pub triggers: Vec<TriggerSpec>,
+    // DP recovery after a break, an enemy without it stays broken
+    #[serde(default)]
+    pub recovery: Option<RecoverySpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecoverySpec {
+    pub turns: u32,
+    #[serde(default = "RecoverySpec::default_dp_rate")]
+    pub dp_rate: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PhaseSpec {
+    #[serde(default = "PhaseSpec::default_hp_rate")]
+    pub hp_rate: f32,
+    pub cycle: Vec<EnemyTurnSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerSpec {
+    pub when: TurnCondition,
+    pub actions: Vec<EnemyActionSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyTurnSpec {
+    pub actions: Vec<EnemyActionSpec>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyActionSpec {
+    pub skill: String,
+    #[serde(default = "EnemyActionSpec::default_target")]
+    pub target: Targeting,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TurnSpec {
+    pub actions: Vec<ActionSpec>,
+    #[serde(default)]
+    pub overdrive: Option<ActivationSpec>,
+    // played before the enemies act, one per level activated on the turn
+    #[serde(default)]
+    pub overdrive_turns: Vec<TurnSpec>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverdriveTiming {
+    // before the actions of the turn, so they can spend the SP boost
+    #[default]
+    Start,
+    // after the actions, so the gauge they fill can be spent
+    Interrupt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActivationSpec {
+    pub level: u32,
+    #[serde(default)]
+    pub timing: OverdriveTiming,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActionSpec {
+    pub actor: String,
+    // one of skill and swap, swap names the back member taking the actor's slot
+    #[serde(default)]
+    pub skill: Option<String>,
+    #[serde(default)]
+    pub swap: Option<String>,
+    #[serde(default)]
+    pub target: usize,
+    #[serde(default)]
+    pub ally: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    Io(std::io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    UnknownFormat(String),
+    Invalid { context: String, reason: String },
+}
+
+impl CatalogueError {
+    fn invalid(context: &str, reason: &str) -> Self {
+        Self::Invalid {
+            context: context.to_owned(),
+            reason: reason.to_owned(),
+        }
+    }
+}
+
+impl AbilitiesSpec {
+    // in the order of AbilityName::ALL
+    pub fn values(&self) -> [i32; 6] {
+        [
+            self.strength,
+            self.dexterity,
+            self.stamina,
+            self.endurement,
+            self.luck,
+            self.intelligence,
+        ]
+    }
+}
+
+impl ModelSpec {
+    pub fn formula(&self, context: &str) -> Result<ModelFormula, CatalogueError> {
+        let model_type = match self.kind {
+            ModelKind::Single => AbilityModelType::Single,
+            ModelKind::Equal => AbilityModelType::Equal,
+            ModelKind::WeightedOnPrior => AbilityModelType::WeigthedOnPrior,
+            ModelKind::Weighted => {
+                if self.weights.len() != self.abilities.len() {
+                    return Err(CatalogueError::invalid(
+                        context,
+                        "weights and abilities differ in length",
+                    ));
+                }
+                return ModelFormula::weighted(
+                    self.abilities
+                        .iter()
+                        .copied()
+                        .zip(self.weights.iter().copied())
+                        .collect(),
+                    self.rounding,
+                )
+                .ok_or_else(|| CatalogueError::invalid(context, "weights add up to zero"));
+            }
+        };
+        if self.abilities.len() != model_type.weights().len() {
+            return Err(CatalogueError::invalid(
+                context,
+                &format!(
+                    "{:?} needs {} abilities, got {}",
+                    self.kind,
+                    model_type.weights().len(),
+                    self.abilities.len()
+                ),
+            ));
+        }
+        ModelFormula::preset(
+            model_type,
+            self.abilities[0],
+            self.abilities.get(1).copied(),
+        )
+        .map_err(|kind| CatalogueError::invalid(context, &format!("{:?} is incomplete", kind)))
+    }
+}
+
+impl EffectSpec {
+    fn default_kind() -> EffectKindSpec {
+        EffectKindSpec::Damage
+    }
+
+    // None for damage, recover, SP and zones, which have their own constructors
+    fn buff_kind(&self) -> Option<EffectKind> {
+        let element = self.element.unwrap_or(Element::None);
+        let ability = self.ability.unwrap_or(AbilityName::Strength);
+        Some(match self.kind {
+            EffectKindSpec::AttackUp => EffectKind::Buff(BuffStat::AttackUp),
+            EffectKindSpec::ElementAttackUp => EffectKind::Buff(BuffStat::ElementAttackUp(element)),
+            EffectKindSpec::CriticalRateUp => EffectKind::Buff(BuffStat::CriticalRateUp),
+            EffectKindSpec::CriticalDamageUp => EffectKind::Buff(BuffStat::CriticalDamageUp),
+            EffectKindSpec::DefenseDown => EffectKind::Buff(BuffStat::DefenseDown),
+            EffectKindSpec::ElementDefenseDown => {
+                EffectKind::Buff(BuffStat::ElementDefenseDown(element))
+            }
+            EffectKindSpec::AbilityUp => EffectKind::AbilityUp(ability),
+            EffectKindSpec::AbilityDown => EffectKind::AbilityDown(ability),
+            EffectKindSpec::Regen => {
+                EffectKind::Buff(BuffStat::Regen(self.pool.unwrap_or(Pool::Dp)))
+            }
+            _ => return None,
+        })
+    }
+
+    fn validate(&self, context: &str) -> Result<(), CatalogueError> {
+        let context = format!("{}/{}", context, self.name);
+        if self.name.is_empty() {
+            return Err(CatalogueError::invalid(&context, "effect name is empty"));
+        }
+        let elemental = matches!(
+            self.kind,
+            EffectKindSpec::ElementAttackUp
+                | EffectKindSpec::ElementDefenseDown
+                | EffectKindSpec::Field
+        );
+        if elemental && self.element.is_none() {
+            return Err(CatalogueError::invalid(&context, "element is missing"));
+        }
+        let ability = matches!(
+            self.kind,
+            EffectKindSpec::AbilityUp | EffectKindSpec::AbilityDown
+        );
+        if ability && self.ability.is_none() {
+            return Err(CatalogueError::invalid(&context, "ability is missing"));
+        }
+        // effects on the party find nobody to land on with an enemy scope
+        let party = matches!(
+            self.kind,
+            EffectKindSpec::Recover
+                | EffectKindSpec::Sp
+                | EffectKindSpec::SpCostDown
+                | EffectKindSpec::AbilityUp
+                | EffectKindSpec::AbilityDown
+                | EffectKindSpec::Regen
+        );
+        if party && self.scope.is_some_and(|scope| scope.is_enemy()) {
+            return Err(CatalogueError::invalid(
+                &context,
+                "the effect only lands on the party",
+            ));
+        }
+        if let Some(model) = &self.model {
+            model.formula(&context)?;
+        }
+        if self.overheal.is_some_and(|overheal| overheal < 1.0) {
+            return Err(CatalogueError::invalid(&context, "overheal is below 1"));
+        }
+        if self.break_bonus.is_some_and(|bonus| bonus < 0.0) {
+            return Err(CatalogueError::invalid(&context, "break bonus is negative"));
+        }
+        if self.min < 0.0 {
+            return Err(CatalogueError::invalid(&context, "min is negative"));
+        }
+        if self.border < 0 {
+            return Err(CatalogueError::invalid(&context, "border is negative"));
+        }
+        if self.max.is_some_and(|max| max < self.min) {
+            return Err(CatalogueError::invalid(&context, "max is below min"));
+        }
+        // buffs, debuffs and zones would never wear off, SP cost downs last a turn by default
+        let lasting = matches!(self.kind, EffectKindSpec::Field | EffectKindSpec::Zone);
+        if (self.buff_kind().is_some() || lasting) && self.duration.is_none() {
+            return Err(CatalogueError::invalid(&context, "duration is missing"));
+        }
+        if self.duration == Some(0) {
+            return Err(CatalogueError::invalid(&context, "duration is zero"));
+        }
+        let hits = self.hits.unwrap_or(1);
+        if hits == 0 {
+            return Err(CatalogueError::invalid(&context, "hits is zero"));
+        }
+        if self.max_hits.is_some_and(|max_hits| max_hits < hits) {
+            return Err(CatalogueError::invalid(&context, "max hits is below hits"));
+        }
+        if !self.hit_weights.is_empty() && self.hit_weights.len() != hits as usize {
+            return Err(CatalogueError::invalid(
+                &context,
+                "hit weights do not match the hits",
+            ));
+        }
+        if self.hit_weights.iter().any(|weight| *weight < 0.0) {
+            return Err(CatalogueError::invalid(
+                &context,
+                "a hit weight is negative",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> SkillEffect {
+        let mut effect = match self.kind {
+            EffectKindSpec::Damage => SkillEffect::new_damage(&self.name, self.min, self.border),
+            EffectKindSpec::Recover => SkillEffect::new_heal(
+                &self.name,
+                self.pool.unwrap_or(Pool::Dp),
+                self.min,
+                self.border,
+            ),
+            EffectKindSpec::Sp => SkillEffect::new_sp(&self.name, self.min.round() as i32),
+            EffectKindSpec::SpCostDown => SkillEffect::new_sp_cost_down(
+                &self.name,
+                self.min.round() as i32,
+                self.duration.unwrap_or(1),
+            ),
+            EffectKindSpec::Field | EffectKindSpec::Zone => SkillEffect::new_zone(
+                &self.name,
+                match self.kind {
+                    EffectKindSpec::Field => Layer::Field,
+                    _ => Layer::Zone,
+                },
+                self.min,
+                self.max.unwrap_or(self.min),
+                self.border,
+            ),
+            _ => SkillEffect::new_buff(
+                &self.name,
+                self.buff_kind().expect("every other kind is a buff"),
+                self.min,
+                self.max.unwrap_or(self.min),
+                self.border,
+            ),
+        };
+        // damage and recovery derive their max from min, a given max replaces it
+        if let (Some(max), EffectKindSpec::Damage | EffectKindSpec::Recover) = (self.max, self.kind)
+        {
+            effect.reset_damage(self.min, max);
+        }
+        if let Some(scope) = self.scope {
+            effect.set_scope(scope);
+        }
+        if let Some(element) = self.element {
+            effect.set_element(element);
+        }
+        effect.set_attack_type(self.attack_type);
+        effect.set_rates(self.dp_rate.unwrap_or(1.0), self.hp_rate.unwrap_or(1.0));
+        if let Some(destruction) = self.destruction {
+            effect.set_destruction(destruction);
+        }
+        if self.duration.is_some() {
+            effect.set_duration(self.duration);
+        }
+        let hits = self.hits.unwrap_or(1);
+        effect.set_hit_range(hits, self.max_hits.unwrap_or(hits));
+        effect.set_hit_weights(self.hit_weights.clone());
+        effect.set_model(self.model.as_ref().map(|model| {
+            model
+                .formula(&self.name)
+                .expect("the model was checked by validate")
+        }));
+        if let Some(overheal) = self.overheal {
+            effect.set_overheal(overheal);
+        }
+        if let Some(bonus) = self.break_bonus {
+            effect.set_break_bonus(bonus);
+        }
+        effect.set_condition(self.condition);
+        effect
+    }
+}
+
+impl SkillSpec {
+    fn validate(&self, context: &str) -> Result<(), CatalogueError> {
+        let context = format!("{}/{}", context, self.name);
+        if self.name.is_empty() {
+            return Err(CatalogueError::invalid(&context, "skill name is empty"));
+        }
+        if self.sp_cost < 0 {
+            return Err(CatalogueError::invalid(&context, "SP cost is negative"));
+        }
+        if self.od_rate.is_some_and(|od_rate| od_rate < 0.0) {
+            return Err(CatalogueError::invalid(&context, "OD rate is negative"));
+        }
+        if self.level == Some(0) {
+            return Err(CatalogueError::invalid(&context, "skill level is zero"));
+        }
+        if self.level_rate.is_some_and(|level_rate| level_rate < 0.0) {
+            return Err(CatalogueError::invalid(&context, "level rate is negative"));
+        }
+        if self.effects.is_empty() {
+            return Err(CatalogueError::invalid(&context, "skill has no effect"));
+        }
+        self.effects
+            .iter()
+            .try_for_each(|effect| effect.validate(&context))
+    }
+
+    pub fn build(&self) -> Skill {
+        let mut skill = Skill::new(&self.name);
+        skill.set_sp_cost(self.sp_cost);
+        if let Some(od_rate) = self.od_rate {
+            skill.set_od_rate(od_rate);
+        }
+        for effect in self.effects.iter() {
+            skill.add_effect(effect.build());
+        }
+        match self.level {
+            Some(level) => level_skill(&skill, level, self.level_rate.unwrap_or(SKILL_LEVEL_RATE)),
+            None => skill,
+        }
+    }
+}
+
+impl GrowthSpec {
+    pub fn table(&self, base: [i32; 6]) -> GrowthTable {
+        let mut table = GrowthTable::new(base, self.max_level, self.max_limit_break);
+        for (ability, gain) in self.per_level.iter() {
+            table.set_per_level(*ability, *gain);
+        }
+        for (ability, gain) in self.per_limit_break.iter() {
+            table.set_per_limit_break(*ability, *gain);
+        }
+        table
+    }
+
+    pub fn growth(&self) -> Growth {
+        let mut growth = Growth::new(self.level, self.limit_break);
+        for (ability, value) in self.enhancement.iter() {
+            growth.add_enhancement(*ability, *value);
+        }
+        growth
+    }
+}
+
+impl PassiveSpec {
+    fn validate(&self, context: &str) -> Result<(), CatalogueError> {
+        let context = format!("{}/{}", context, self.name);
+        if self.name.is_empty() {
+            return Err(CatalogueError::invalid(&context, "passive name is empty"));
+        }
+        if self.effects.is_empty() {
+            return Err(CatalogueError::invalid(&context, "passive has no effect"));
+        }
+        if let Trigger::HpBelow(rate) = self.trigger {
+            if rate <= 0.0 || rate > 1.0 {
+                return Err(CatalogueError::invalid(
+                    &context,
+                    "HP rate must be in (0, 1]",
+                ));
+            }
+        }
+        if self.limit == Some(0) {
+            return Err(CatalogueError::invalid(&context, "limit is zero"));
+        }
+        self.effects
+            .iter()
+            .try_for_each(|effect| effect.validate(&context))
+    }
+
+    pub fn build(&self) -> Passive {
+        let mut skill = Skill::new(&self.name);
+        for effect in self.effects.iter() {
+            skill.add_effect(effect.build());
+        }
+        let mut passive = Passive::new(self.trigger, skill);
+        for condition in self.conditions.iter() {
+            passive.add_condition(*condition);
+        }
+        passive.set_limit(self.limit);
+        passive
+    }
+}
+
+impl AccessorySpec {
+    fn validate(&self, context: &str) -> Result<(), CatalogueError> {
+        let context = format!("{}/{}", context, self.name);
+        if self.name.is_empty() {
+            return Err(CatalogueError::invalid(&context, "accessory name is empty"));
+        }
+        if self.percent.values().any(|rate| *rate <= -1.0) {
+            return Err(CatalogueError::invalid(
+                &context,
+                "percent takes the whole ability",
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn build(&self) -> Accessory {
+        let mut accessory = Accessory::new(&self.name);
+        for ability in AbilityName::ALL {
+            if let Some(value) = self.flat.get(&ability) {
+                accessory.add_flat(ability, *value);
+            }
+            if let Some(rate) = self.percent.get(&ability) {
+                accessory.add_percent(ability, *rate);
+            }
+        }
+        accessory
+    }
+}
+
+impl StyleSpec {
+    fn validate(&self) -> Result<(), CatalogueError> {
+        if self.name.is_empty() {
+            return Err(CatalogueError::invalid("style", "style name is empty"));
+        }
+        self.formula()?;
+        self.abilities()?;
+        if let Some(sp) = self.sp {
+            if sp.cap <= 0 || sp.initial < 0 || sp.regen < 0 {
+                return Err(CatalogueError::invalid(
+                    &self.name,
+                    "SP values are out of range",
+                ));
+            }
+        }
+        if self.dp.is_some_and(|dp| dp < 0.0) || self.hp.is_some_and(|hp| hp < 0.0) {
+            return Err(CatalogueError::invalid(&self.name, "DP or HP is negative"));
+        }
+        for (index, skill) in self.skills.iter().enumerate() {
+            skill.validate(&self.name)?;
+            if self.skills[..index]
+                .iter()
+                .any(|other| other.name == skill.name)
+            {
+                return Err(CatalogueError::invalid(
+                    &format!("{}/{}", self.name, skill.name),
+                    "skill name is duplicated",
+                ));
+            }
+        }
+        for passive in self.passives.iter() {
+            passive.validate(&self.name)?;
+        }
+        for skill in self.swap_passives.iter() {
+            skill.validate(&format!("{}/swap", self.name))?;
+        }
+        // accessories are taken off by name, so two of the same would come off together
+        for (index, accessory) in self.accessories.iter().enumerate() {
+            accessory.validate(&self.name)?;
+            if self.accessories[..index]
+                .iter()
+                .any(|other| other.name == accessory.name)
+            {
+                return Err(CatalogueError::invalid(
+                    &format!("{}/{}", self.name, accessory.name),
+                    "accessory name is duplicated",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // the base abilities grown to the level and limit break of the style
+    pub fn abilities(&self) -> Result<[i32; 6], CatalogueError> {
+        let base = self.abilities.values();
+        match &self.growth {
+            Some(growth) => growth
+                .table(base)
+                .abilities(&growth.growth())
+                .map_err(|error| CatalogueError::invalid(&self.name, &error.to_string())),
+            None => Ok(base),
+        }
+    }
+
+    pub fn loadout(&self) -> Loadout {
+        let mut loadout = Loadout::new();
+        loadout.set_weapon(
+            self.weapon
+                .as_ref()
+                .map(|weapon| Weapon::new(&weapon.name, weapon.element, weapon.attack_type)),
+        );
+        for accessory in self.accessories.iter() {
+            loadout.add_accessory(accessory.build());
+        }
+        loadout
+    }
+
+    pub fn skills(&self) -> Vec<Skill> {
+        self.skills.iter().map(|skill| skill.build()).collect()
+    }
+
+    pub fn formula(&self) -> Result<Formula, CatalogueError> {
+        let mut formula = Formula {
+            attack: self.attack.formula(&format!("{}/attack", self.name))?,
+            ..Formula::default()
+        };
+        if let Some(defense) = &self.defense {
+            formula.defense = defense.formula(&format!("{}/defense", self.name))?;
+        }
+        if let Some(heal) = &self.heal {
+            formula.heal = heal.formula(&format!("{}/heal", self.name))?;
+        }
+        Ok(formula)
+    }
+
+    pub fn character(&self) -> Result<Character, CatalogueError> {
+        let mut character = Character::new(&self.name, self.abilities()?, &self.formula()?);
+        if let Some(sp) = self.sp {
+            character.set_sp(SpPool::new(sp.initial, sp.cap, sp.regen));
+        }
+        if self.dp.is_some() || self.hp.is_some() {
+            character.set_vitals(self.dp.unwrap_or(0.0), self.hp.unwrap_or(0.0));
+        }
+        for skill in self.skills() {
+            character.add_skill(skill);
+        }
+        for passive in self.passives.iter() {
+            character.add_passive(passive.build());
+        }
+        for skill in self.swap_passives.iter() {
+            character.add_swap_passive(skill.build());
+        }
+        character.equip(self.loadout());
+        Ok(character)
+    }
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T, CatalogueError> {
+    let text = fs::read_to_string(path).map_err(CatalogueError::Io)?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => toml::from_str(&text).map_err(CatalogueError::Toml),
+        Some("json") => serde_json::from_str(&text).map_err(CatalogueError::Json),
+        _ => Err(CatalogueError::UnknownFormat(path.display().to_string())),
+    }
+}
+
+fn validate_styles(styles: &[StyleSpec]) -> Result<(), CatalogueError> {
+    for (index, style) in styles.iter().enumerate() {
+        style.validate()?;
+        if styles[..index].iter().any(|other| other.name == style.name) {
+            return Err(CatalogueError::invalid(
+                &style.name,
+                "style name is duplicated",
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Catalogue {
+    pub fn from_toml(text: &str) -> Result<Self, CatalogueError> {
+        let catalogue: Self = toml::from_str(text).map_err(CatalogueError::Toml)?;
+        catalogue.validate()?;
+        Ok(catalogue)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, CatalogueError> {
+        let catalogue: Self = serde_json::from_str(text).map_err(CatalogueError::Json)?;
+        catalogue.validate()?;
+        Ok(catalogue)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CatalogueError> {
+        let catalogue: Self = read(path.as_ref())?;
+        catalogue.validate()?;
+        Ok(catalogue)
+    }
+
+    pub fn validate(&self) -> Result<(), CatalogueError> {
+        validate_styles(&self.styles)
+    }
+
+    // later files override styles of the same name
+    pub fn merge(&mut self, other: Catalogue) {
+        for style in other.styles {
+            self.styles.retain(|known| known.name != style.name);
+            self.styles.push(style);
+        }
+    }
+
+    pub fn style(&self, name: &str) -> Option<&StyleSpec> {
+        self.styles.iter().find(|style| style.name == name)
+    }
+}
+
+impl RecoverySpec {
+    fn default_dp_rate() -> f32 {
+        1.0
+    }
+}
+
+impl PhaseSpec {
+    fn default_hp_rate() -> f32 {
+        1.0
+    }
+}
+
+impl EnemyActionSpec {
+    fn default_target() -> Targeting {
+        Targeting::Random
+    }
+}
+
+impl EnemySpec {
+    fn validate(&self) -> Result<(), CatalogueError> {
+        let context = format!("enemies/{}", self.name);
+        if self.name.is_empty() {
+            return Err(CatalogueError::invalid("enemies", "enemy name is empty"));
+        }
+        for skill in self.skills.iter() {
+            skill.validate(&context)?;
+        }
+        if let Some(recovery) = &self.recovery {
+            if recovery.turns == 0 || recovery.dp_rate <= 0.0 || recovery.dp_rate > 1.0 {
+                return Err(CatalogueError::invalid(
+                    &context,
+                    "recovery needs turns and a DP rate in (0, 1]",
+                ));
+            }
+        }
+        let mut previous = 1.0;
+        for phase in self.phases.iter().skip(1) {
+            if phase.hp_rate <= 0.0 || phase.hp_rate > previous {
+                return Err(CatalogueError::invalid(
+                    &context,
+                    "phase HP rates must fall from 1 to above 0",
+                ));
+            }
+            previous = phase.hp_rate;
+        }
+        let actions: Vec<&EnemyActionSpec> = self
+            .phases
+            .iter()
+            .flat_map(|phase| phase.cycle.iter().flat_map(|turn| turn.actions.iter()))
+            .chain(
+                self.triggers
+                    .iter()
+                    .flat_map(|trigger| trigger.actions.iter()),
+            )
+            .collect();
+        if !actions.is_empty() && self.attack.is_none() {
+            return Err(CatalogueError::invalid(&context, "actions need an attack"));
+        }
+        for action in actions {
+            if !self.skills.iter().any(|skill| skill.name == action.skill) {
+                return Err(CatalogueError::invalid(
+                    &context,
+                    &format!("there is no skill {}", action.skill),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn actions(&self, specs: &[EnemyActionSpec]) -> Vec<EnemyAction> {
+        specs
+            .iter()
+            .filter_map(|spec| {
+                let skill = self
+                    .skills
+                    .iter()
+                    .position(|skill| skill.name == spec.skill)?;
+                Some(EnemyAction::new(skill, spec.target))
+            })
+            .collect()
+    }
+
+    fn pattern(&self) -> Pattern {
+        let cycle = |phase: &PhaseSpec| -> Vec<Vec<EnemyAction>> {
+            phase
+                .cycle
+                .iter()
+                .map(|turn| self.actions(&turn.actions))
+                .collect()
+        };
+        let mut phases = self.phases.iter();
+        let mut pattern = Pattern::new(phases.next().map(cycle).unwrap_or_default());
+        for phase in phases {
+            pattern.add_phase(phase.hp_rate, cycle(phase));
+        }
+        for trigger in self.triggers.iter() {
+            pattern.add_trigger(trigger.when, self.actions(&trigger.actions));
+        }
+        pattern
+    }
+
+    pub fn build(&self) -> Enemy {
+        let mut target = Target::new(&self.name, self.dp, self.hp);
+        if let Some(max_destruction) = self.max_destruction {
+            target.set_max_destruction(max_destruction);
+        }
+        for (element, rate) in self.elements.iter() {
+            target.set_element_rate(*element, *rate);
+        }
+        for (attack_type, rate) in self.attack_types.iter() {
+            target.set_attack_type_rate(*attack_type, *rate);
+        }
+        target.set_recovery(
+            self.recovery
+                .as_ref()
+                .map(|recovery| (recovery.turns, recovery.dp_rate)),
+        );
+        let model = |ability: Ability| {
+            let helper: AbilityModifierHelper = AbilityModifier::from(ability).into();
+            AbilityModel::new(AbilityModelType::Single, helper.get_cell(), None)
+                .expect("a single model needs one ability")
+        };
+        let mut enemy = Enemy::new(target, model(Ability::Stamina(self.defense)));
+        enemy.set_luck(model(Ability::Luck(self.luck)));
+        if let Some(attack) = self.attack {
+            enemy.set_attack(model(Ability::Strength(attack)));
+        }
+        for skill in self.skills.iter() {
+            enemy.add_skill(skill.build());
+        }
+        enemy.set_pattern(self.pattern());
+        enemy
+    }
+}
+
+impl BattleSpec {
+    fn default_max_turns() -> u32 {
+        30
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, CatalogueError> {
+        let spec: Self = toml::from_str(text).map_err(CatalogueError::Toml)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, CatalogueError> {
+        let spec: Self = serde_json::from_str(text).map_err(CatalogueError::Json)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    // styles written in the battle file win over the included ones
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, CatalogueError> {
+        let path = path.as_ref();
+        let mut spec: Self = read(path)?;
+        let mut catalogue = Catalogue::default();
+        for include in spec.include.drain(..) {
+            let include = path.parent().unwrap_or(Path::new(".")).join(include);
+            catalogue.merge(Catalogue::load(include)?);
+        }
+        catalogue.merge(Catalogue {
+            styles: std::mem::take(&mut spec.styles),
+        });
+        spec.styles = catalogue.styles;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn member_index(&self, name: &str) -> Result<usize, CatalogueError> {
+        self.party
+            .iter()
+            .position(|member| member == name)
+            .ok_or_else(|| CatalogueError::invalid(name, "not in the party"))
+    }
+
+    fn style(&self, name: &str) -> Result<&StyleSpec, CatalogueError> {
+        self.styles
+            .iter()
+            .find(|style| style.name == name)
+            .ok_or_else(|| CatalogueError::invalid(name, "unknown style"))
+    }
+
+    pub fn validate(&self) -> Result<(), CatalogueError> {
+        validate_styles(&self.styles)?;
+        if self.enemies.is_empty() {
+            return Err(CatalogueError::invalid("enemies", "there is no enemy"));
+        }
+        for enemy in self.enemies.iter() {
+            enemy.validate()?;
+        }
+        for name in self.party.iter() {
+            self.style(name)?;
+        }
+        if let Some(overdrive) = &self.overdrive {
+            if overdrive.gauge < 0.0 || overdrive.hit_rate.is_some_and(|rate| rate < 0.0) {
+                return Err(CatalogueError::invalid(
+                    "overdrive",
+                    "gauge and hit rate cannot be negative",
+                ));
+            }
+            if overdrive
+                .sp_boosts
+                .is_some_and(|sp_boosts| sp_boosts.iter().any(|&sp| sp < 0))
+            {
+                return Err(CatalogueError::invalid(
+                    "overdrive",
+                    "SP boosts cannot be negative",
+                ));
+            }
+        }
+        for (turn, spec) in self.rotation.iter().enumerate() {
+            let context = format!("rotation/{}", turn + 1);
+            self.validate_turn(&context, spec)?;
+            for (extra, overdrive) in spec.overdrive_turns.iter().enumerate() {
+                let context = format!("{}/overdrive/{}", context, extra + 1);
+                if !overdrive.overdrive_turns.is_empty() {
+                    return Err(CatalogueError::invalid(
+                        &context,
+                        "overdrive turns belong to the turn that starts the overdrive",
+                    ));
+                }
+                self.validate_turn(&context, overdrive)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_turn(&self, context: &str, spec: &TurnSpec) -> Result<(), CatalogueError> {
+        if let Some(activation) = spec.overdrive {
+            if activation.level == 0 || activation.level > OD_LEVELS {
+                return Err(CatalogueError::invalid(
+                    context,
+                    &format!("overdrive level must be from 1 to {}", OD_LEVELS),
+                ));
+            }
+        }
+        for action in spec.actions.iter() {
+            self.member_index(&action.actor)?;
+            if let Some(ally) = &action.ally {
+                self.member_index(ally)?;
+            }
+            match (&action.skill, &action.swap) {
+                (Some(skill), None) => {
+                    if !self
+                        .style(&action.actor)?
+                        .skills
+                        .iter()
+                        .any(|other| other.name == *skill)
+                    {
+                        return Err(CatalogueError::invalid(
+                            context,
+                            &format!("{} has no skill {}", action.actor, skill),
+                        ));
+                    }
+                }
+                (None, Some(swap)) => {
+                    self.member_index(swap)?;
+                }
+                _ => {
+                    return Err(CatalogueError::invalid(
+                        context,
+                        &format!("{} needs either a skill or a swap", action.actor),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn rotation(&self) -> Result<ScriptedRotation, CatalogueError> {
+        let mut rotation = ScriptedRotation::new();
+        for spec in self.rotation.iter() {
+            rotation.add_turn(self.actions(spec)?);
+            for overdrive in spec.overdrive_turns.iter() {
+                rotation.add_overdrive_turn(self.actions(overdrive)?);
+            }
+        }
+        Ok(rotation)
+    }
+
+    fn actions(&self, spec: &TurnSpec) -> Result<Vec<Action>, CatalogueError> {
+        let mut actions = vec![];
+        for action in spec.actions.iter() {
+            let actor = self.member_index(&action.actor)?;
+            let name = match (&action.skill, &action.swap) {
+                (_, Some(swap)) => {
+                    actions.push(Action::Swap {
+                        out: actor,
+                        into: self.member_index(swap)?,
+                    });
+                    continue;
+                }
+                (Some(name), None) => name,
+                (None, None) => {
+                    return Err(CatalogueError::invalid(&action.actor, "there is no skill"))
+                }
+            };
+            let skill = self
+                .style(&action.actor)?
+                .skills
+                .iter()
+                .position(|skill| skill.name == *name)
+                .ok_or_else(|| CatalogueError::invalid(name, "unknown skill"))?;
+            actions.push(match &action.ally {
+                Some(ally) => Action::Skill {
+                    actor,
+                    skill,
+                    target: action.target,
+                    ally: self.member_index(ally)?,
+                },
+                None => Action::skill(actor, skill, action.target),
+            });
+        }
+        if let Some(activation) = spec.overdrive {
+            let overdrive = Action::Overdrive {
+                level: activation.level,
+            };
+            match activation.timing {
+                OverdriveTiming::Start => actions.insert(0, overdrive),
+                OverdriveTiming::Interrupt => actions.push(overdrive),
+            }
+        }
+        Ok(actions)
+    }
+
+    pub fn battle(&self) -> Result<Battle, CatalogueError> {
+        let party = self
+            .party
+            .iter()
+            .map(|name| self.style(name)?.character())
+            .collect::<Result<Vec<Character>, CatalogueError>>()?;
+        let enemies = self.enemies.iter().map(|enemy| enemy.build()).collect();
+        let mut battle = Battle::new(party, enemies)
+            .map_err(|error| CatalogueError::invalid("battle", &error.to_string()))?;
+        if let Some(spec) = &self.overdrive {
+            let mut overdrive = Overdrive::new(spec.gauge);
+            if let Some(hit_rate) = spec.hit_rate {
+                overdrive.set_hit_rate(hit_rate);
+            }
+            if let Some(sp_boosts) = spec.sp_boosts {
+                overdrive.set_sp_boosts(sp_boosts);
+            }
+            battle.set_overdrive(overdrive);
+        }
+        battle.set_swap_cost(self.swap_cost);
+        Ok(battle)
+    }
+}
+
+impl Display for CatalogueError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "cannot read catalogue: {}", error),
+            Self::Toml(error) => write!(f, "invalid TOML: {}", error),
+            Self::Json(error) => write!(f, "invalid JSON: {}", error),
+            Self::UnknownFormat(path) => write!(f, "{} is neither .toml nor .json", path),
+            Self::Invalid { context, reason } => write!(f, "{}: {}", context, reason),
+        }
+    }
+}
+
+impl std::error::Error for CatalogueError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::{BattleLog, Rotation};
+
+    const STYLE: &str = r#"
+[[styles]]
+name = "Ruka"
+abilities = { strength = 300, dexterity = 240, stamina = 200, endurement = 180, luck = 150, intelligence = 120 }
+attack = { kind = "weighted_on_prior", abilities = ["strength", "dexterity"] }
+sp = { initial = 6, cap = 20, regen = 2 }
+weapon = { name = "Flame Sword", element = "fire", attack_type = "slash" }
+
+[[styles.skills]]
+name = "Flame Slash"
+sp_cost = 7
+
+[[styles.skills.effects]]
+name = "Flame Slash"
+min = 1200.0
+border = 150
+element = "fire"
+attack_type = "slash"
+hp_rate = 1.2
+destruction = 0.15
+
+[[styles.skills]]
+name = "Fire Up"
+sp_cost = 4
+
+[[styles.skills.effects]]
+name = "Fire Up"
+kind = "element_attack_up"
+element = "fire"
+min = 0.3
+max = 0.5
+border = 100
+duration = 2
+model = { kind = "single", abilities = ["intelligence"] }
+
+[[styles.passives]]
+name = "Last Stand"
+trigger = { hp_below = 0.3 }
+conditions = ["front", { sp_at_least = 5 }]
+limit = 1
+
+[[styles.passives.effects]]
+name = "Last Stand"
+kind = "attack_up"
+min = 0.2
+duration = 2
+
+[[styles.swap_passives]]
+name = "Rally"
+
+[[styles.swap_passives.effects]]
+name = "Rally"
+kind = "attack_up"
+min = 0.1
+duration = 1
+
+[[styles.accessories]]
+name = "Power Ring"
+flat = { strength = 20 }
+percent = { dexterity = 0.05 }
+"#;
+
+    fn invalid(text: &str) -> bool {
+        matches!(
+            Catalogue::from_toml(text),
+            Err(CatalogueError::Invalid { .. })
+        )
+    }
+
+    #[test]
+    fn load_style() {
+        let catalogue = Catalogue::from_toml(STYLE).expect("it should succeed");
+        let style = catalogue.style("Ruka").expect("it should exist");
+        let skills = style.skills();
+        assert_eq!(skills[0].sp_cost(), 7);
+        let effect = skills[0].effects().next().expect("it should exist");
+        assert_eq!(effect.max(), 6000.0);
+        assert_eq!(effect.element(), Element::Fire);
+        assert_eq!(effect.attack_type(), Some(AttackType::Slash));
+        let character = style.character().expect("it should succeed");
+        assert_eq!(character.sp().current(), 6);
+    }
+
+    #[test]
+    fn round_trip_json() {
+        let catalogue = Catalogue::from_toml(STYLE).expect("it should succeed");
+        let json = serde_json::to_string(&catalogue).expect("it should succeed");
+        assert_eq!(
+            Catalogue::from_json(&json).expect("it should succeed"),
+            catalogue
+        );
+    }
+
+    #[test]
+    fn recover_builds_heal() {
+        let text = STYLE.replace("min = 1200.0", "kind = \"recover\"\nmin = 1200.0");
+        let catalogue = Catalogue::from_toml(&text).expect("it should succeed");
+        let skills = catalogue.style("Ruka").expect("it should exist").skills();
+        let effect = skills[0].effects().next().expect("it should exist");
+        assert_eq!(effect.kind(), EffectKind::Heal(Pool::Dp));
+        assert_eq!(effect.scope(), Scope::Ally);
+    }
+
+    #[test]
+    fn reject_model_missing_ability() {
+        assert!(invalid(
+            &STYLE.replace("[\"strength\", \"dexterity\"]", "[\"strength\"]")
+        ));
+    }
+
+    #[test]
+    fn reject_negative_min() {
+        assert!(invalid(&STYLE.replace("min = 1200.0", "min = -1200.0")));
+    }
+
+    #[test]
+    fn reject_negative_border() {
+        assert!(invalid(&STYLE.replace("border = 150", "border = -1")));
+    }
+
+    #[test]
+    fn reject_unknown_element() {
+        let text = STYLE.replace("element = \"fire\"", "element = \"wind\"");
+        assert!(matches!(
+            Catalogue::from_toml(&text),
+            Err(CatalogueError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn reject_enemy_scoped_sp() {
+        assert!(invalid(&STYLE.replace(
+            "min = 1200.0",
+            "kind = \"sp\"\nscope = \"enemy\"\nmin = 1200.0"
+        )));
+    }
+
+    #[test]
+    fn reject_mismatched_hit_weights() {
+        assert!(invalid(
+            &STYLE.replace("destruction = 0.15", "hits = 2\nhit_weights = [1.0]")
+        ));
+    }
+
+    #[test]
+    fn load_buff() {
+        let catalogue = Catalogue::from_toml(STYLE).expect("it should succeed");
+        let skills = catalogue.style("Ruka").expect("it should exist").skills();
+        let effect = skills[1].effects().next().expect("it should exist");
+        assert_eq!(
+            effect.kind(),
+            EffectKind::Buff(BuffStat::ElementAttackUp(Element::Fire))
+        );
+        assert_eq!(
+            effect.model(),
+            Some(&ModelFormula::single(AbilityName::Intelligence))
+        );
+    }
+
+    #[test]
+    fn reject_ability_up_without_ability() {
+        assert!(invalid(&STYLE.replace(
+            "min = 1200.0",
+            "kind = \"ability_up\"\nduration = 2\nmin = 30.0"
+        )));
+    }
+
+    #[test]
+    fn reject_buff_without_duration() {
+        assert!(invalid(
+            &STYLE.replace("min = 0.2\nduration = 2", "min = 0.2")
+        ));
+    }
+
+    #[test]
+    fn load_field() {
+        let text = STYLE.replace("kind = \"element_attack_up\"", "kind = \"field\"");
+        assert!(Catalogue::from_toml(&text).is_ok());
+    }
+
+    #[test]
+    fn reject_field_without_element() {
+        let text = STYLE
+            .replace("kind = \"element_attack_up\"", "kind = \"field\"")
+            .replace("element = \"fire\"\nmin = 0.3", "min = 0.3");
+        assert!(invalid(&text));
+    }
+
+    #[test]
+    fn load_zone() {
+        let text = STYLE.replace(
+            "kind = \"attack_up\"\nmin = 0.2",
+            "kind = \"zone\"\nmin = 0.2",
+        );
+        assert!(Catalogue::from_toml(&text).is_ok());
+    }
+
+    #[test]
+    fn reject_zone_without_duration() {
+        let text = STYLE.replace(
+            "kind = \"attack_up\"\nmin = 0.2\nduration = 2",
+            "kind = \"zone\"\nmin = 0.2",
+        );
+        assert!(invalid(&text));
+    }
+
+    #[test]
+    fn load_passives() {
+        let catalogue = Catalogue::from_toml(STYLE).expect("it should succeed");
+        let style = catalogue.style("Ruka").expect("it should exist");
+        let character = style.character().expect("it should succeed");
+        let passive = &character.passives()[0];
+        assert_eq!(passive.trigger(), Trigger::HpBelow(0.3));
+        assert_eq!(passive.conditions()[1], Condition::SpAtLeast(5));
+        assert_eq!(
+            character
+                .swap_passives()
+                .map(Skill::name)
+                .collect::<Vec<_>>(),
+            vec!["Rally"]
+        );
+    }
+
+    #[test]
+    fn reject_threshold_above_one() {
+        assert!(invalid(&STYLE.replace("hp_below = 0.3", "hp_below = 1.5")));
+    }
+
+    #[test]
+    fn load_equipment() {
+        let catalogue = Catalogue::from_toml(STYLE).expect("it should succeed");
+        let style = catalogue.style("Ruka").expect("it should exist");
+        let character = style.character().expect("it should succeed");
+        // the ring's 20 strength and 5% dexterity on top of 280
+        assert_eq!(character.attack().value(), 297);
+        let weapon = character.loadout().weapon().expect("it should exist");
+        assert_eq!(weapon.attack_type(), AttackType::Slash);
+    }
+
+    #[test]
+    fn reject_negative_accessory_percent() {
+        assert!(invalid(
+            &STYLE.replace("dexterity = 0.05", "dexterity = -1.0")
+        ));
+    }
+
+    fn grown() -> String {
+        STYLE
+            .replace("sp_cost = 7\n", "sp_cost = 7\nlevel = 11\n")
+            .replace(
+                "sp = {",
+                "growth = { level = 11, limit_break = 1, max_level = 120, max_limit_break = 4, \
+             per_level = { strength = 2.0 }, per_limit_break = { strength = 10 } }\nsp = {",
+            )
+    }
+
+    #[test]
+    fn load_growth() {
+        let catalogue = Catalogue::from_toml(&grown()).expect("it should succeed");
+        let style = catalogue.style("Ruka").expect("it should exist");
+        assert_eq!(style.abilities().expect("it should succeed")[0], 330);
+        let character = style.character().expect("it should succeed");
+        assert_eq!(character.attack().value(), 317);
+        let effect = character.skills()[0]
+            .effects()
+            .next()
+            .expect("it should exist");
+        assert_eq!(effect.max().round(), 7200.0);
+    }
+
+    #[test]
+    fn reject_level_above_max() {
+        let catalogue = Catalogue::from_toml(&grown()).expect("it should succeed");
+        let text = toml::to_string(&catalogue).expect("it should succeed");
+        assert!(invalid(&text.replace("level = 11", "level = 130")));
+    }
+
+    fn battle_text() -> String {
+        format!(
+            "party = [\"Ruka\"]\n{}\n{}",
+            r#"
+[[enemies]]
+name = "Cancer"
+dp = 1000.0
+hp = 20000.0
+defense = 200
+luck = 150
+elements = { fire = 2.0 }
+
+[[rotation]]
+actions = [{ actor = "Ruka", skill = "Flame Slash" }]
+"#,
+            STYLE.replace("initial = 6", "initial = 20")
+        )
+    }
+
+    fn run(text: &str) -> BattleLog {
+        let spec = BattleSpec::from_toml(text).expect("it should succeed");
+        let mut battle = spec.battle().expect("it should succeed");
+        let mut rotation = spec.rotation().expect("it should succeed");
+        battle
+            .run(&mut rotation, spec.max_turns)
+            .expect("it should succeed")
+    }
+
+    #[test]
+    fn battle_kill_turn() {
+        let bare = battle_text().replace(
+            "flat = { strength = 20 }\npercent = { dexterity = 0.05 }",
+            "",
+        );
+        // 7824 then 10378 HP after the DP breaks, the third hit kills
+        assert_eq!(run(&bare).kill_turn, Some(3));
+    }
+
+    #[test]
+    fn reject_idle_action() {
+        let text = battle_text().replace(", skill = \"Flame Slash\"", "");
+        assert!(BattleSpec::from_toml(&text).is_err());
+    }
+
+    #[test]
+    fn reject_unknown_skill() {
+        let text = battle_text().replace("skill = \"Flame Slash\"", "skill = \"Ice Slash\"");
+        assert!(BattleSpec::from_toml(&text).is_err());
+    }
+
+    #[test]
+    fn battle_enemy_attack() {
+        let text = battle_text()
+            .replace(
+                "elements = { fire = 2.0 }",
+                r#"elements = { fire = 2.0 }
+attack = 400
+phases = [{ cycle = [{ actions = [{ skill = "Crush", target = { slot = 0 } }] }] }]
+
+[[enemies.skills]]
+name = "Crush"
+
+[[enemies.skills.effects]]
+name = "Crush"
+min = 500.0
+border = 200"#,
+            )
+            .replace("regen = 2 }", "regen = 2 }\ndp = 1000.0\nhp = 1500.0");
+        // 2500 at the border breaks the 1000 DP and the rest takes all 1500 HP
+        assert_eq!(run(&text).wipe_turn, Some(1));
+    }
+
+    #[test]
+    fn battle_enemy_recovery() {
+        let text = battle_text().replace(
+            "defense = 200",
+            "defense = 200\nrecovery = { turns = 2, dp_rate = 0.5 }",
+        );
+        let spec = BattleSpec::from_toml(&text).expect("it should succeed");
+        assert_eq!(spec.enemies[0].build().target().recovery(), Some((2, 0.5)));
+    }
+
+    fn overdrive_text() -> String {
+        battle_text().replace(
+            "skill = \"Flame Slash\" }]",
+            r#"skill = "Flame Slash" }]
+overdrive = { level = 1, timing = "interrupt" }
+overdrive_turns = [{ actions = [{ actor = "Ruka", skill = "Fire Up" }] }]"#,
+        )
+    }
+
+    #[test]
+    fn battle_overdrive_rotation() {
+        let mut rotation = BattleSpec::from_toml(&overdrive_text())
+            .expect("it should succeed")
+            .rotation()
+            .expect("it should succeed");
+        assert_eq!(rotation.actions(1)[1], Action::Overdrive { level: 1 });
+        assert_eq!(
+            rotation.overdrive_actions(1, 1),
+            vec![Action::skill(0, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn reject_overdrive_level() {
+        let text = overdrive_text().replace("level = 1", "level = 4");
+        assert!(BattleSpec::from_toml(&text).is_err());
+    }
+
+    #[test]
+    fn reject_negative_sp_boosts() {
+        let text = format!("{}\n[overdrive]\nsp_boosts = [5, -1, 20]\n", battle_text());
+        assert!(matches!(
+            BattleSpec::from_toml(&text),
+            Err(CatalogueError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn battle_swap_cost() {
+        let text = format!("swap_cost = \"action\"\n{}", battle_text());
+        let spec = BattleSpec::from_toml(&text).expect("it should succeed");
+        assert_eq!(
+            spec.battle().expect("it should succeed").swap_cost(),
+            SwapCost::Action
+        );
+    }
+
+    #[test]
+    fn battle_accessory() {
+        // the ring lifts the second hit past the HP left after the DP breaks
+        assert_eq!(run(&battle_text()).kill_turn, Some(2));
+    }
+}
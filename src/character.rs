@@ -0,0 +1,438 @@
+use crate::ability::{
+    AbilityModel, AbilityModelType, AbilityModifier, AbilityModifierHelper, AbilityName,
+    ModelFormula,
+};
+use crate::buff::{Buff, BuffCategory, BuffStat, StatBuffs};
+use crate::critical::Critical;
+use crate::equipment::Loadout;
+use crate::passive::{Passive, Trigger};
+use crate::sampling::Rng;
+use crate::skill::{DamageCalculator, EffectKind, Pool, Skill, SkillEffect};
+use crate::sp::SpPool;
+use crate::target::{Enemy, Hit};
+use crate::vitals::Vitals;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formula {
+    pub attack: ModelFormula,
+    pub defense: ModelFormula,
+    pub heal: ModelFormula,
+}
+
+pub struct Character {
+    name: String,
+    formula: Formula,
+    modifiers: Vec<AbilityModifierHelper>,
+    attack: AbilityModel,
+    defense: AbilityModel,
+    heal: AbilityModel,
+    luck: AbilityModel,
+    skills: Vec<Skill>,
+    passives: Vec<Passive>,
+    loadout: Loadout,
+    sp: SpPool,
+    buffs: StatBuffs,
+    vitals: Vitals,
+}
+
+impl Default for Formula {
+    fn default() -> Self {
+        Self {
+            attack: ModelFormula::pair(
+                AbilityModelType::WeigthedOnPrior,
+                AbilityName::Strength,
+                AbilityName::Dexterity,
+            ),
+            defense: ModelFormula::pair(
+                AbilityModelType::WeigthedOnPrior,
+                AbilityName::Stamina,
+                AbilityName::Endurement,
+            ),
+            heal: ModelFormula::pair(
+                AbilityModelType::WeigthedOnPrior,
+                AbilityName::Endurement,
+                AbilityName::Intelligence,
+            ),
+        }
+    }
+}
+
+// a deep copy, the clone's modifiers are its own
+impl Clone for Character {
+    fn clone(&self) -> Self {
+        let modifiers: Vec<AbilityModifierHelper> = self
+            .modifiers
+            .iter()
+            .map(|modifier| modifier.detached())
+            .collect();
+        Self {
+            name: self.name.clone(),
+            formula: self.formula.clone(),
+            attack: Self::build(&modifiers, &self.formula.attack),
+            defense: Self::build(&modifiers, &self.formula.defense),
+            heal: Self::build(&modifiers, &self.formula.heal),
+            luck: Self::build(&modifiers, &ModelFormula::single(AbilityName::Luck)),
+            modifiers,
+            skills: self.skills.clone(),
+            passives: self.passives.clone(),
+            loadout: self.loadout.clone(),
+            sp: self.sp.clone(),
+            buffs: self.buffs.clone(),
+            vitals: self.vitals,
+        }
+    }
+}
+
+impl Character {
+    // abilities in the order of AbilityName::ALL
+    pub fn new(name: &str, abilities: [i32; 6], formula: &Formula) -> Self {
+        let modifiers: Vec<AbilityModifierHelper> = AbilityName::ALL
+            .iter()
+            .zip(abilities)
+            .map(|(ability, value)| AbilityModifier::from(ability.ability(value)).into())
+            .collect();
+        Self {
+            name: name.to_owned(),
+            formula: formula.clone(),
+            attack: Self::build(&modifiers, &formula.attack),
+            defense: Self::build(&modifiers, &formula.defense),
+            heal: Self::build(&modifiers, &formula.heal),
+            luck: Self::build(&modifiers, &ModelFormula::single(AbilityName::Luck)),
+            modifiers,
+            skills: vec![],
+            passives: vec![],
+            loadout: Loadout::new(),
+            sp: SpPool::default(),
+            buffs: StatBuffs::new(),
+            vitals: Vitals::default(),
+        }
+    }
+
+    fn build(modifiers: &[AbilityModifierHelper], formula: &ModelFormula) -> AbilityModel {
+        AbilityModel::weighted(
+            formula
+                .weights()
+                .iter()
+                .map(|(ability, weight)| (modifiers[*ability as usize].get_cell(), *weight))
+                .collect(),
+            formula.rounding(),
+        )
+        .expect("a formula always has weight")
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    pub fn modifier(&self, ability: AbilityName) -> &AbilityModifierHelper {
+        &self.modifiers[ability as usize]
+    }
+
+    // for skills that scale off something other than the standard models
+    pub fn model(&self, formula: &ModelFormula) -> AbilityModel {
+        Self::build(&self.modifiers, formula)
+    }
+
+    pub fn attack(&self) -> &AbilityModel {
+        &self.attack
+    }
+
+    pub fn defense(&self) -> &AbilityModel {
+        &self.defense
+    }
+
+    pub fn heal(&self) -> &AbilityModel {
+        &self.heal
+    }
+
+    pub fn luck(&self) -> &AbilityModel {
+        &self.luck
+    }
+
+    pub fn add_skill(&mut self, skill: Skill) {
+        self.skills.push(skill);
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+
+    pub fn skill(&self, name: &str) -> Option<&Skill> {
+        self.skills.iter().find(|skill| skill.name() == name)
+    }
+
+    pub fn add_passive(&mut self, passive: Passive) {
+        self.passives.push(passive);
+    }
+
+    pub fn passives(&self) -> &[Passive] {
+        &self.passives
+    }
+
+    pub fn passives_mut(&mut self) -> &mut [Passive] {
+        &mut self.passives
+    }
+
+    // used for free whenever the member is swapped into the front
+    pub fn add_swap_passive(&mut self, skill: Skill) {
+        self.add_passive(Passive::new(Trigger::SwapIn, skill));
+    }
+
+    pub fn swap_passives(&self) -> impl Iterator<Item = &Skill> {
+        self.passives
+            .iter()
+            .filter(|passive| passive.trigger() == Trigger::SwapIn)
+            .map(Passive::skill)
+    }
+
+    pub fn loadout(&self) -> &Loadout {
+        &self.loadout
+    }
+
+    // swaps the gear, its bonuses replace the old ones on the modifiers. returns the old loadout
+    pub fn equip(&mut self, loadout: Loadout) -> Loadout {
+        for modifier in self.modifiers.iter() {
+            let mut modifier = modifier.get_mut();
+            for accessory in self.loadout.accessories() {
+                modifier.remove(&format!("accessory:{}", accessory.name()));
+            }
+            let ability = modifier.ability().clone();
+            for accessory in loadout.accessories() {
+                let bonus = accessory.bonus(ability.name(), ability.value());
+                if bonus != 0.0 {
+                    modifier.apply(Buff::new(
+                        &format!("accessory:{}", accessory.name()),
+                        BuffCategory::Accessory,
+                        bonus,
+                        None,
+                    ));
+                }
+            }
+        }
+        std::mem::replace(&mut self.loadout, loadout)
+    }
+
+    // a damage effect as this member lands it, taking on the weapon's attack type
+    pub fn arm(&self, effect: &SkillEffect) -> SkillEffect {
+        match self.loadout.weapon() {
+            Some(weapon) => weapon.arm(effect),
+            None => effect.clone(),
+        }
+    }
+
+    pub fn set_sp(&mut self, sp: SpPool) {
+        self.sp = sp;
+    }
+
+    pub fn sp(&self) -> &SpPool {
+        &self.sp
+    }
+
+    pub fn sp_mut(&mut self) -> &mut SpPool {
+        &mut self.sp
+    }
+
+    // rate buffs, ability buffs live on the modifiers
+    pub fn buffs(&self) -> &StatBuffs {
+        &self.buffs
+    }
+
+    pub fn buffs_mut(&mut self) -> &mut StatBuffs {
+        &mut self.buffs
+    }
+
+    // DP and HP, nothing tracked until set
+    pub fn vitals(&self) -> &Vitals {
+        &self.vitals
+    }
+
+    pub fn vitals_mut(&mut self) -> &mut Vitals {
+        &mut self.vitals
+    }
+
+    pub fn set_vitals(&mut self, dp: f32, hp: f32) {
+        self.vitals = Vitals::new(dp, hp);
+    }
+
+    // members without HP set are never knocked out
+    pub fn is_dead(&self) -> bool {
+        self.vitals.is_dead()
+    }
+
+    // applies regeneration buffs up to the maximum, returning what each pool got back
+    pub fn regenerate(&mut self) -> Vec<(Pool, f32)> {
+        if self.is_dead() {
+            return vec![];
+        }
+        [Pool::Dp, Pool::Hp]
+            .into_iter()
+            .filter_map(|pool| {
+                let amount = self.buffs.total(BuffStat::Regen(pool));
+                (amount > 0.0).then(|| (pool, self.vitals.restore(pool, amount, 1.0)))
+            })
+            .collect()
+    }
+
+    pub fn tick(&mut self) {
+        for modifier in self.modifiers.iter() {
+            modifier.get_mut().tick();
+        }
+        self.sp.tick();
+        self.buffs.tick();
+    }
+
+    pub fn critical_rate(&self, critical: &Critical, enemy: &Enemy) -> f32 {
+        let defender = enemy.luck().map_or(0, |luck| luck.value());
+        critical.rate_from(self.luck.value() - defender)
+    }
+
+    // an enemy attack, scaled by damage_from_enemy against this member's defense model
+    pub fn take(
+        &mut self,
+        effect: &SkillEffect,
+        attacker_model: &AbilityModel,
+        count: u32,
+        roll: impl FnMut() -> (DamageCalculator, bool),
+    ) -> Vec<Hit> {
+        let base = effect.damage_from_enemy(attacker_model, &self.defense);
+        self.vitals.apply_rolled(effect, base, count, roll)
+    }
+
+    // expected damage of one use against the enemy as it stands, without changing it.
+    // no criticals and a default calculator
+    pub fn skill_damage(&self, skill: &Skill, enemy: &Enemy) -> f32 {
+        self.skill_damage_with(skill, enemy, &DamageCalculator::new(), &Critical::new())
+    }
+
+    // skill_damage under the calculator and critical settings of a battle
+    pub fn skill_damage_with(
+        &self,
+        skill: &Skill,
+        enemy: &Enemy,
+        calculator: &DamageCalculator,
+        critical: &Critical,
+    ) -> f32 {
+        self.roll_damage(skill, enemy, calculator, critical, None)
+    }
+
+    // one use with the spread and critical rolled from rng, expected values without one
+    pub fn roll_damage(
+        &self,
+        skill: &Skill,
+        enemy: &Enemy,
+        calculator: &DamageCalculator,
+        critical: &Critical,
+        mut rng: Option<&mut Rng>,
+    ) -> f32 {
+        let mut target = enemy.target().clone();
+        skill
+            .effects()
+            .filter(|effect| effect.kind() == EffectKind::Damage)
+            .map(|effect| {
+                let effect = &self.arm(effect);
+                let mut calculator = calculator.clone();
+                let mut critical = critical.clone();
+                self.buffs
+                    .modify_attack(effect.element(), &mut calculator, &mut critical);
+                enemy
+                    .buffs()
+                    .modify_defense(effect.element(), &mut calculator);
+                let rate = self.critical_rate(&critical, enemy);
+                let count = effect.roll_hits(rng.as_deref_mut());
+                target
+                    .hit(effect, &self.attack, enemy.defense(), count, || {
+                        let mut calculator = calculator.clone();
+                        calculator.set_roll(rng.as_deref_mut().map(Rng::next_f32));
+                        let rolled = rng.as_deref_mut().map(Rng::next_f32);
+                        let hit = critical.apply(&mut calculator, rate, rolled);
+                        (calculator, hit)
+                    })
+                    .iter()
+                    .map(|hit| hit.dp_damage + hit.hp_damage)
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+}
+
+impl Display for Character {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        writeln!(f, "[{}] {}", self.name, self.sp)?;
+        for modifier in self.modifiers.iter() {
+            write!(f, "{}", modifier.get().ability())?;
+        }
+        write!(
+            f,
+            "\tattack: {}, defense: {}, heal: {}",
+            self.attack.value(),
+            self.defense.value(),
+            self.heal.value()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::{Ability, Rounding};
+    use crate::target::Target;
+    use crate::testing::single;
+
+    #[test]
+    fn standard_models() {
+        let character = Character::new("Ruka", [300, 240, 200, 170, 150, 120], &Formula::default());
+        assert_eq!(character.attack().value(), 280);
+        assert_eq!(character.defense().value(), 190);
+        assert_eq!(character.heal().value(), 153);
+        character
+            .modifier(AbilityName::Strength)
+            .get_mut()
+            .apply_positive(30);
+        assert_eq!(character.attack().value(), 300);
+        assert_eq!(
+            ModelFormula::preset(AbilityModelType::Equal, AbilityName::Strength, None).err(),
+            Some(AbilityModelType::Equal)
+        );
+        let formula = ModelFormula::weighted(
+            vec![
+                (AbilityName::Strength, 1),
+                (AbilityName::Dexterity, 1),
+                (AbilityName::Luck, 1),
+            ],
+            Rounding::Round,
+        )
+        .expect("it should succeed");
+        // (330 + 240 + 150) / 3
+        assert_eq!(character.model(&formula).value(), 240);
+    }
+
+    #[test]
+    fn skill_damage() {
+        let mut character =
+            Character::new("Ruka", [300, 240, 200, 170, 200, 120], &Formula::default());
+        let mut skill = Skill::new("slash");
+        skill.add_effect(SkillEffect::new_damage("slash", 1000.0, 80));
+        character.add_skill(skill);
+        let mut enemy = Enemy::new(
+            Target::new("enemy", 1000.0, 100000.0),
+            single(Ability::Stamina(200)),
+        );
+        enemy.set_luck(single(Ability::Luck(200)));
+        let skill = character.skill("slash").expect("it should exist");
+        // at the border, criticals only count once expected values are opted into
+        assert_eq!(character.skill_damage(skill, &enemy), 5000.0);
+        // a 5% chance of a 1.5x critical
+        let mut critical = Critical::new();
+        critical.set_expected(true);
+        assert_eq!(
+            character.skill_damage_with(skill, &enemy, &DamageCalculator::new(), &critical),
+            5000.0 * 1.025
+        );
+        assert_eq!(enemy.target().dp(), 1000.0);
+    }
+}
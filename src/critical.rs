@@ -0,0 +1,149 @@
+use crate::ability::AbilityModel;
+use crate::skill::DamageCalculator;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone)]
+pub struct Critical {
+    base_rate: f32,
+    luck_factor: f32,
+    rate_up: Vec<f32>,
+    base_damage: f32,
+    damage_up: Vec<f32>,
+    expected: bool,
+}
+
+impl Default for Critical {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Critical {
+    pub fn new() -> Self {
+        Self {
+            base_rate: 0.05,
+            luck_factor: 0.002,
+            rate_up: vec![],
+            base_damage: 1.5,
+            damage_up: vec![],
+            expected: false,
+        }
+    }
+
+    // without a roll, hits land at the expected multiplier instead of never critting
+    pub fn set_expected(&mut self, expected: bool) {
+        self.expected = expected;
+    }
+
+    pub fn is_expected(&self) -> bool {
+        self.expected
+    }
+
+    // luck_factor is the rate gained per point of Luck above the defender
+    pub fn set_base_rate(&mut self, base_rate: f32, luck_factor: f32) {
+        self.base_rate = base_rate;
+        self.luck_factor = luck_factor;
+    }
+
+    pub fn set_base_damage(&mut self, base_damage: f32) {
+        self.base_damage = base_damage;
+    }
+
+    pub fn add_rate_up(&mut self, rate: f32) {
+        self.rate_up.push(rate);
+    }
+
+    pub fn add_damage_up(&mut self, rate: f32) {
+        self.damage_up.push(rate);
+    }
+
+    pub fn rate_from(&self, luck_difference: i32) -> f32 {
+        (self.base_rate
+            + self.luck_factor * luck_difference as f32
+            + self.rate_up.iter().sum::<f32>())
+        .clamp(0.0, 1.0)
+    }
+
+    pub fn rate(&self, attacker_luck: &AbilityModel, defender_luck: &AbilityModel) -> f32 {
+        self.rate_from(attacker_luck.value() - defender_luck.value())
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        self.base_damage + self.damage_up.iter().sum::<f32>()
+    }
+
+    pub fn expected(&self, rate: f32) -> f32 {
+        1.0 + rate * (self.multiplier() - 1.0)
+    }
+
+    // roll in [0, 1), a roll below the rate is a critical hit
+    pub fn sample(&self, rate: f32, roll: f32) -> f32 {
+        if roll < rate {
+            self.multiplier()
+        } else {
+            1.0
+        }
+    }
+
+    // returns whether the roll was a critical hit, always false without a roll
+    pub fn apply(&self, calculator: &mut DamageCalculator, rate: f32, roll: Option<f32>) -> bool {
+        match roll {
+            Some(roll) => {
+                calculator.set_critical(self.sample(rate, roll));
+                roll < rate
+            }
+            None if self.expected => {
+                calculator.set_critical(self.expected(rate));
+                false
+            }
+            None => {
+                calculator.set_critical(1.0);
+                false
+            }
+        }
+    }
+}
+
+impl Display for Critical {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "critical: {}% + {}%/Luck + {}%, x{}",
+            self.base_rate * 100.0,
+            self.luck_factor * 100.0,
+            self.rate_up.iter().sum::<f32>() * 100.0,
+            self.multiplier()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::Ability;
+    use crate::testing::single;
+
+    #[test]
+    fn rate_and_multiplier() {
+        let attacker = single(Ability::Luck(250));
+        let defender = single(Ability::Luck(200));
+        let mut critical = Critical::new();
+        critical.set_base_rate(0.1, 0.004);
+        assert_eq!(critical.rate(&attacker, &defender), 0.3);
+        critical.add_rate_up(0.5);
+        critical.add_damage_up(0.5);
+        assert_eq!(critical.rate(&attacker, &defender), 0.8);
+        assert_eq!(critical.multiplier(), 2.0);
+        assert_eq!(critical.expected(0.5), 1.5);
+        let mut calculator = DamageCalculator::new();
+        assert!(critical.apply(&mut calculator, 0.8, Some(0.79)));
+        assert!(!critical.apply(&mut calculator, 0.8, Some(0.8)));
+        critical.apply(&mut calculator, 0.5, None);
+        assert_eq!(calculator.multiplier(), 1.0);
+        critical.set_expected(true);
+        critical.apply(&mut calculator, 0.5, None);
+        assert_eq!(calculator.multiplier(), 1.5);
+        critical.add_rate_up(1.0);
+        assert_eq!(critical.rate(&attacker, &defender), 1.0);
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    Fire,
+    Ice,
+    Thunder,
+    Light,
+    Dark,
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttackType {
+    Slash,
+    Stab,
+    Strike,
+}
+
+impl Element {
+    pub fn typename(&self) -> &'static str {
+        match self {
+            Self::Fire => "Fire",
+            Self::Ice => "Ice",
+            Self::Thunder => "Thunder",
+            Self::Light => "Light",
+            Self::Dark => "Dark",
+            Self::None => "None",
+        }
+    }
+}
+
+impl AttackType {
+    pub fn typename(&self) -> &'static str {
+        match self {
+            Self::Slash => "Slash",
+            Self::Stab => "Stab",
+            Self::Strike => "Strike",
+        }
+    }
+}
+
+impl Display for Element {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.typename())
+    }
+}
+
+impl Display for AttackType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.typename())
+    }
+}
@@ -0,0 +1,206 @@
+use crate::ability::AbilityName;
+use crate::element::{AttackType, Element};
+use crate::skill::SkillEffect;
+
+// percent boosts are rates of the base ability, 0.1 adds a tenth
+#[derive(Debug, Clone, PartialEq)]
+pub struct Accessory {
+    name: String,
+    flat: Vec<(AbilityName, i32)>,
+    percent: Vec<(AbilityName, f32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Weapon {
+    name: String,
+    element: Element,
+    attack_type: AttackType,
+}
+
+// the gear a member carries into battle, swapped as a whole
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Loadout {
+    weapon: Option<Weapon>,
+    accessories: Vec<Accessory>,
+}
+
+impl Accessory {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            flat: vec![],
+            percent: vec![],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn add_flat(&mut self, ability: AbilityName, value: i32) {
+        self.flat.push((ability, value));
+    }
+
+    pub fn add_percent(&mut self, ability: AbilityName, rate: f32) {
+        self.percent.push((ability, rate));
+    }
+
+    pub fn flat(&self) -> &[(AbilityName, i32)] {
+        &self.flat
+    }
+
+    pub fn percent(&self) -> &[(AbilityName, f32)] {
+        &self.percent
+    }
+
+    // what the accessory adds on top of the base value of the ability
+    pub fn bonus(&self, ability: AbilityName, base: i32) -> f32 {
+        let flat: i32 = self
+            .flat
+            .iter()
+            .filter(|(name, _)| *name == ability)
+            .map(|(_, value)| value)
+            .sum();
+        let rate: f32 = self
+            .percent
+            .iter()
+            .filter(|(name, _)| *name == ability)
+            .map(|(_, rate)| rate)
+            .sum();
+        flat as f32 + base as f32 * rate
+    }
+}
+
+impl Weapon {
+    pub fn new(name: &str, element: Element, attack_type: AttackType) -> Self {
+        Self {
+            name: name.to_owned(),
+            element,
+            attack_type,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn attack_type(&self) -> AttackType {
+        self.attack_type
+    }
+
+    // the effect as swung with this weapon, its own element and attack type come first.
+    // a non-elemental skill lands with the weapon's element and meets the enemy's rate for it
+    pub fn arm(&self, effect: &SkillEffect) -> SkillEffect {
+        let mut effect = effect.clone();
+        if effect.element() == Element::None {
+            effect.set_element(self.element);
+        }
+        if effect.attack_type().is_none() {
+            effect.set_attack_type(Some(self.attack_type));
+        }
+        effect
+    }
+}
+
+impl Loadout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn weapon(&self) -> Option<&Weapon> {
+        self.weapon.as_ref()
+    }
+
+    pub fn set_weapon(&mut self, weapon: Option<Weapon>) {
+        self.weapon = weapon;
+    }
+
+    pub fn add_accessory(&mut self, accessory: Accessory) {
+        self.accessories.push(accessory);
+    }
+
+    pub fn accessories(&self) -> &[Accessory] {
+        &self.accessories
+    }
+
+    pub fn bonus(&self, ability: AbilityName, base: i32) -> f32 {
+        self.accessories
+            .iter()
+            .map(|accessory| accessory.bonus(ability, base))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::Ability;
+    use crate::buff::{Buff, BuffCategory};
+    use crate::character::{Character, Formula};
+    use crate::skill::Skill;
+    use crate::target::{Enemy, Target};
+    use crate::testing::single;
+
+    #[test]
+    fn swap_loadouts() {
+        let mut member = Character::new("a", [200, 100, 100, 100, 100, 100], &Formula::default());
+        let base = member.attack().value();
+        let mut ring = Accessory::new("ring");
+        ring.add_flat(AbilityName::Strength, 20);
+        ring.add_percent(AbilityName::Strength, 0.1);
+        assert_eq!(ring.bonus(AbilityName::Strength, 200), 40.0);
+        assert_eq!(ring.bonus(AbilityName::Luck, 200), 0.0);
+        let mut loadout = Loadout::new();
+        loadout.add_accessory(ring);
+        loadout.set_weapon(Some(Weapon::new("blade", Element::Fire, AttackType::Slash)));
+        member.equip(loadout.clone());
+        assert_eq!(
+            member.modifier(AbilityName::Strength).get_mut().value(),
+            240
+        );
+        assert!(member.attack().value() > base);
+        let previous = member.equip(Loadout::new());
+        assert_eq!(previous, loadout);
+        assert_eq!(member.attack().value(), base);
+        // a skill buff sharing the accessory's name stays on when it comes off
+        member.equip(loadout.clone());
+        member
+            .modifier(AbilityName::Strength)
+            .get_mut()
+            .apply(Buff::new("ring", BuffCategory::SkillBuff, 10.0, Some(1)));
+        member.equip(Loadout::new());
+        assert_eq!(
+            member.modifier(AbilityName::Strength).get_mut().value(),
+            210
+        );
+
+        let weapon = loadout.weapon().expect("it should succeed");
+        let mut effect = SkillEffect::new_damage("cut", 100.0, 300);
+        effect.set_element(Element::Ice);
+        let armed = weapon.arm(&effect);
+        assert_eq!(armed.element(), Element::Ice);
+        assert_eq!(armed.attack_type(), Some(AttackType::Slash));
+        let armed = weapon.arm(&SkillEffect::new_damage("cut", 100.0, 300));
+        assert_eq!(armed.element(), Element::Fire);
+    }
+
+    #[test]
+    fn weapon_element() {
+        let mut member = Character::new("a", [200, 100, 100, 100, 100, 100], &Formula::default());
+        let mut skill = Skill::new("cut");
+        skill.add_effect(SkillEffect::new_damage("cut", 1000.0, 100));
+        let mut target = Target::new("enemy", 0.0, 100000.0);
+        target.set_element_rate(Element::Fire, 2.0);
+        let enemy = Enemy::new(target, single(Ability::Stamina(100)));
+        let bare = member.skill_damage(&skill, &enemy);
+        let mut loadout = Loadout::new();
+        loadout.set_weapon(Some(Weapon::new("blade", Element::Fire, AttackType::Slash)));
+        member.equip(loadout);
+        // the fire blade doubles a non-elemental cut against a fire weakness
+        assert_eq!(member.skill_damage(&skill, &enemy), bare * 2.0);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::element::Element;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// each layer holds one zone at a time, elemental fields share the Field layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layer {
+    Field,
+    Zone,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    name: String,
+    element: Option<Element>,
+    rate: f32,
+    duration: Option<u32>,
+}
+
+// battlefield-wide state seen by both sides
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Battlefield {
+    zones: HashMap<Layer, Zone>,
+}
+
+impl Zone {
+    // element None boosts damage of every element, duration in turns and None never ends
+    pub fn new(name: &str, element: Option<Element>, rate: f32, duration: Option<u32>) -> Self {
+        Self {
+            name: name.to_owned(),
+            element,
+            rate,
+            duration,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn element(&self) -> Option<Element> {
+        self.element
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    pub fn duration(&self) -> Option<u32> {
+        self.duration
+    }
+
+    pub fn affects(&self, element: Element) -> bool {
+        self.element.is_none_or(|own| own == element)
+    }
+}
+
+impl Battlefield {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn zone(&self, layer: Layer) -> Option<&Zone> {
+        self.zones.get(&layer)
+    }
+
+    // a new zone always overwrites the one on its layer, whatever their rates
+    pub fn lay(&mut self, layer: Layer, zone: Zone) -> Option<Zone> {
+        self.zones.insert(layer, zone)
+    }
+
+    pub fn clear(&mut self, layer: Layer) -> Option<Zone> {
+        self.zones.remove(&layer)
+    }
+
+    // the field rate for damage of the element, summed over the layers
+    pub fn rate(&self, element: Element) -> f32 {
+        self.zones
+            .values()
+            .filter(|zone| zone.affects(element))
+            .map(Zone::rate)
+            .sum()
+    }
+
+    // returns the zones that ran out
+    pub fn tick(&mut self) -> Vec<Zone> {
+        let mut expired = vec![];
+        for zone in self.zones.values_mut() {
+            if let Some(duration) = zone.duration.as_mut() {
+                *duration = duration.saturating_sub(1);
+            }
+        }
+        self.zones.retain(|_, zone| {
+            let ended = zone.duration == Some(0);
+            if ended {
+                expired.push(zone.clone());
+            }
+            !ended
+        });
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overwrite_and_expire() {
+        let mut battlefield = Battlefield::new();
+        battlefield.lay(
+            Layer::Field,
+            Zone::new("fire field", Some(Element::Fire), 0.5, Some(3)),
+        );
+        battlefield.lay(Layer::Zone, Zone::new("zone", None, 0.1, Some(1)));
+        assert_eq!(battlefield.rate(Element::Fire), 0.6);
+        assert_eq!(battlefield.rate(Element::Ice), 0.1);
+        let replaced = battlefield.lay(
+            Layer::Field,
+            Zone::new("ice field", Some(Element::Ice), 0.3, Some(2)),
+        );
+        assert_eq!(replaced.map(|zone| zone.rate()), Some(0.5));
+        assert_eq!(battlefield.rate(Element::Fire), 0.1);
+        let expired = battlefield.tick();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(battlefield.rate(Element::Ice), 0.3);
+        battlefield.tick();
+        assert!(battlefield.zone(Layer::Field).is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use crate::battle::{FRONT_SIZE, PARTY_SIZE};
+
+// which member stands in each slot, the first FRONT_SIZE slots are the front row.
+// members are party indices so buffs and SP follow them from slot to slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formation {
+    slots: [Option<usize>; PARTY_SIZE],
+}
+
+impl Formation {
+    // members in party order, the front fills first
+    pub fn new(members: usize) -> Self {
+        let mut slots = [None; PARTY_SIZE];
+        for (slot, member) in slots.iter_mut().zip(0..members) {
+            *slot = Some(member);
+        }
+        Self { slots }
+    }
+
+    // None when a member shows up twice
+    pub fn from_slots(slots: [Option<usize>; PARTY_SIZE]) -> Option<Self> {
+        let members: Vec<usize> = slots.iter().flatten().copied().collect();
+        if (1..members.len()).any(|index| members[..index].contains(&members[index])) {
+            return None;
+        }
+        Some(Self { slots })
+    }
+
+    pub fn member(&self, slot: usize) -> Option<usize> {
+        self.slots.get(slot).copied().flatten()
+    }
+
+    pub fn slot(&self, member: usize) -> Option<usize> {
+        self.slots.iter().position(|slot| *slot == Some(member))
+    }
+
+    // in slot order
+    pub fn front(&self) -> Vec<usize> {
+        self.slots[..FRONT_SIZE].iter().flatten().copied().collect()
+    }
+
+    pub fn back(&self) -> Vec<usize> {
+        self.slots[FRONT_SIZE..].iter().flatten().copied().collect()
+    }
+
+    pub fn is_front(&self, member: usize) -> bool {
+        self.slot(member).is_some_and(|slot| slot < FRONT_SIZE)
+    }
+
+    // trades the slots of two members, false when either is not placed
+    pub fn swap(&mut self, a: usize, b: usize) -> bool {
+        let (Some(a), Some(b)) = (self.slot(a), self.slot(b)) else {
+            return false;
+        };
+        self.slots.swap(a, b);
+        a != b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_rows() {
+        let mut formation = Formation::new(5);
+        assert_eq!(formation.front(), vec![0, 1, 2]);
+        assert_eq!(formation.back(), vec![3, 4]);
+        assert!(formation.swap(1, 4));
+        assert_eq!(formation.front(), vec![0, 4, 2]);
+        assert!(formation.is_front(4) && !formation.is_front(1));
+        assert_eq!(formation.slot(1), Some(4));
+        assert!(!formation.swap(1, 5));
+        assert!(Formation::from_slots([Some(0), Some(0), None, None, None, None]).is_none());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::ability::AbilityName;
+use crate::skill::{EffectKind, Skill};
+use std::fmt::{self, Display, Formatter};
+
+// power gained by every skill level over the first
+pub const SKILL_LEVEL_RATE: f32 = 0.02;
+
+// abilities of a style in the order of AbilityName::ALL, the base is at level 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthTable {
+    base: [i32; 6],
+    per_level: [f32; 6],
+    per_limit_break: [i32; 6],
+    max_level: u32,
+    max_limit_break: u32,
+}
+
+// where a member stands on the table, enhancement is the flat gain of stat-boost items
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Growth {
+    level: u32,
+    limit_break: u32,
+    enhancement: [i32; 6],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthError {
+    Level { level: u32, max: u32 },
+    LimitBreak { limit_break: u32, max: u32 },
+}
+
+impl GrowthTable {
+    pub fn new(base: [i32; 6], max_level: u32, max_limit_break: u32) -> Self {
+        Self {
+            base,
+            per_level: [0.0; 6],
+            per_limit_break: [0; 6],
+            max_level,
+            max_limit_break,
+        }
+    }
+
+    pub fn base(&self) -> [i32; 6] {
+        self.base
+    }
+
+    pub fn set_per_level(&mut self, ability: AbilityName, gain: f32) {
+        self.per_level[ability as usize] = gain;
+    }
+
+    pub fn set_per_limit_break(&mut self, ability: AbilityName, gain: i32) {
+        self.per_limit_break[ability as usize] = gain;
+    }
+
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+
+    pub fn max_limit_break(&self) -> u32 {
+        self.max_limit_break
+    }
+
+    // level gains are truncated once over the whole climb, not level by level
+    pub fn abilities(&self, growth: &Growth) -> Result<[i32; 6], GrowthError> {
+        if growth.level == 0 || growth.level > self.max_level {
+            return Err(GrowthError::Level {
+                level: growth.level,
+                max: self.max_level,
+            });
+        }
+        if growth.limit_break > self.max_limit_break {
+            return Err(GrowthError::LimitBreak {
+                limit_break: growth.limit_break,
+                max: self.max_limit_break,
+            });
+        }
+        let levels = (growth.level - 1) as f32;
+        let mut abilities = self.base;
+        for (index, ability) in abilities.iter_mut().enumerate() {
+            *ability += (self.per_level[index] * levels) as i32
+                + self.per_limit_break[index] * growth.limit_break as i32
+                + growth.enhancement[index];
+        }
+        Ok(abilities)
+    }
+}
+
+impl Growth {
+    pub fn new(level: u32, limit_break: u32) -> Self {
+        Self {
+            level,
+            limit_break,
+            enhancement: [0; 6],
+        }
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn limit_break(&self) -> u32 {
+        self.limit_break
+    }
+
+    pub fn set_limit_break(&mut self, limit_break: u32) {
+        self.limit_break = limit_break;
+    }
+
+    pub fn enhancement(&self) -> [i32; 6] {
+        self.enhancement
+    }
+
+    pub fn add_enhancement(&mut self, ability: AbilityName, value: i32) {
+        self.enhancement[ability as usize] += value;
+    }
+}
+
+// damage and recover effects grow by rate for every level over 1, buff rates stay put
+pub fn level_skill(skill: &Skill, level: u32, rate: f32) -> Skill {
+    let scale = 1.0 + rate * level.saturating_sub(1) as f32;
+    let mut skill = skill.clone();
+    for effect in skill.effects_mut() {
+        if matches!(effect.kind(), EffectKind::Damage | EffectKind::Heal(_)) {
+            effect.reset_damage(effect.min() * scale, effect.max() * scale);
+        }
+    }
+    skill
+}
+
+impl Display for GrowthError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Level { level, max } => {
+                write!(f, "level {} is outside 1 to {}", level, max)
+            }
+            Self::LimitBreak { limit_break, max } => {
+                write!(f, "limit break {} is over the most of {}", limit_break, max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GrowthError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::{Character, Formula};
+    use crate::skill::SkillEffect;
+
+    #[test]
+    fn limit_break_reaches_border() {
+        let mut table = GrowthTable::new([200, 180, 150, 150, 100, 100], 120, 4);
+        table.set_per_level(AbilityName::Strength, 1.5);
+        table.set_per_level(AbilityName::Dexterity, 1.2);
+        table.set_per_limit_break(AbilityName::Strength, 8);
+        table.set_per_limit_break(AbilityName::Dexterity, 8);
+        let mut growth = Growth::new(101, 2);
+        growth.add_enhancement(AbilityName::Strength, 10);
+        let abilities = table.abilities(&growth).expect("it should succeed");
+        assert_eq!(abilities[0], 200 + 150 + 16 + 10);
+        assert_eq!(abilities[1], 180 + 120 + 16);
+        let member = Character::new("a", abilities, &Formula::default());
+        let border = member.attack().value() + 5;
+        growth.set_limit_break(3);
+        let member = Character::new(
+            "a",
+            table.abilities(&growth).expect("it should succeed"),
+            &Formula::default(),
+        );
+        assert!(member.attack().value() >= border);
+        growth.set_limit_break(5);
+        assert_eq!(
+            table.abilities(&growth),
+            Err(GrowthError::LimitBreak {
+                limit_break: 5,
+                max: 4
+            })
+        );
+        assert!(table.abilities(&Growth::new(0, 0)).is_err());
+
+        let mut skill = Skill::new("slash");
+        skill.add_effect(SkillEffect::new_damage("slash", 1000.0, 300));
+        let leveled = level_skill(&skill, 11, SKILL_LEVEL_RATE);
+        let effect = leveled.effects().next().expect("it should exist");
+        assert_eq!(effect.min(), 1200.0);
+        assert_eq!(effect.max(), 6000.0);
+    }
+}
@@ -1,2 +1,23 @@
 pub mod ability;
+pub mod battle;
+pub mod buff;
+pub mod catalogue;
+pub mod character;
+pub mod critical;
+pub mod element;
+pub mod equipment;
+pub mod field;
+pub mod formation;
+pub mod growth;
+pub mod overdrive;
+pub mod passive;
+pub mod pattern;
+pub mod sampling;
 pub mod skill;
+pub mod sp;
+pub mod target;
+pub mod targets;
+pub mod vitals;
+
+#[cfg(test)]
+mod testing;
@@ -1,3 +1,413 @@
-fn main() {
-    println!("Hello, world!");
+use hbr::ability::{
+    Ability, AbilityModel, AbilityModelType, AbilityModifier, AbilityModifierHelper,
+};
+use hbr::battle::{Battle, ScriptedRotation};
+use hbr::catalogue::{BattleSpec, Catalogue};
+use hbr::character::Character;
+use hbr::sampling::Sampler;
+use hbr::skill::{DamageCalculator, EffectKind, SkillEffect};
+use serde_json::json;
+use std::{collections::HashMap, env, process::ExitCode, str::FromStr};
+
+const USAGE: &str = "usage:
+    hbr damage --attack <stat> --defense <stat> --min <value> --border <value> [options]
+    hbr damage --catalogue <file> --skill <name> [--style <name>] --defense <stat> [options]
+        [--attack <stat>] [--min <value>] [--max <value>] [--border <value>]
+        [--attack-up <rate>]... [--defense-down <rate>]...
+        [--element-rate <rate>] [--weapon-rate <rate>] [--critical <multiplier>]
+        [--destruction <rate>] [--field <rate>] [--json]
+    hbr curve --min <value> --border <value> [--max <value>] [--from <sd>] [--to <sd>]
+        [--step <sd>] [--enemy] [--json]
+    hbr curve --catalogue <file> --skill <name> [--style <name>] [...]
+    hbr simulate <battle file> [--turns <count>] [--runs <count> [--seed <seed>]] [--json]";
+
+const FLAGS: [&str; 2] = ["json", "enemy"];
+
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Vec<String>>,
+    flags: Vec<String>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self {
+            positional: vec![],
+            options: HashMap::new(),
+            flags: vec![],
+        };
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if FLAGS.contains(&name) => parsed.flags.push(name.to_owned()),
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", name))?;
+                    parsed
+                        .options
+                        .entry(name.to_owned())
+                        .or_default()
+                        .push(value);
+                }
+                None => parsed.positional.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+
+    fn all<T: FromStr>(&self, name: &str) -> Result<Vec<T>, String> {
+        self.options
+            .get(name)
+            .map_or(&vec![], |values| values)
+            .iter()
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("--{} has an invalid value {}", name, value))
+            })
+            .collect()
+    }
+
+    fn optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        Ok(self.all(name)?.pop())
+    }
+
+    fn required<T: FromStr>(&self, name: &str) -> Result<T, String> {
+        self.optional(name)?
+            .ok_or_else(|| format!("--{} is required", name))
+    }
+}
+
+fn model(ability: Ability) -> AbilityModel {
+    let helper: AbilityModifierHelper = AbilityModifier::from(ability).into();
+    AbilityModel::new(AbilityModelType::Single, helper.get_cell(), None)
+        .expect("a single model needs one ability")
+}
+
+// the style with the skill named by --skill, only --style when given, and the
+// skill's damage effect
+fn catalogue_skill(args: &Args) -> Result<Option<(Character, SkillEffect)>, String> {
+    let Some(path) = args.optional::<String>("catalogue")? else {
+        return Ok(None);
+    };
+    let name: String = args.required("skill")?;
+    let style: Option<String> = args.optional("style")?;
+    let catalogue = Catalogue::load(path).map_err(|error| error.to_string())?;
+    let style = catalogue
+        .styles
+        .iter()
+        .filter(|spec| style.as_ref().is_none_or(|style| *style == spec.name))
+        .find(|spec| spec.skills.iter().any(|skill| skill.name == name))
+        .ok_or_else(|| format!("no style has the skill {}", name))?;
+    let character = style.character().map_err(|error| error.to_string())?;
+    let effect = character
+        .skills()
+        .iter()
+        .find(|skill| skill.name() == name)
+        .and_then(|skill| {
+            skill
+                .effects()
+                .find(|effect| effect.kind() == EffectKind::Damage)
+        })
+        .cloned()
+        .ok_or_else(|| format!("{} does no damage", name))?;
+    Ok(Some((character, effect)))
+}
+
+// --min, --max and --border override the values of a catalogue skill
+fn effect(args: &Args, effect: Option<SkillEffect>) -> Result<SkillEffect, String> {
+    let mut effect = match effect {
+        Some(effect) => effect,
+        None => SkillEffect::new_damage("effect", args.required("min")?, args.required("border")?),
+    };
+    let min = args.optional("min")?.unwrap_or(effect.min());
+    let max = args.optional("max")?.unwrap_or(effect.max());
+    effect.reset_damage(min, max);
+    if let Some(border) = args.optional("border")? {
+        effect.set_border(border);
+    }
+    Ok(effect)
+}
+
+fn damage(args: &Args) -> Result<String, String> {
+    let skill = catalogue_skill(args)?;
+    // --attack overrides the attack model of the catalogue style
+    let attack = match (args.optional("attack")?, &skill) {
+        (Some(attack), _) => model(Ability::Strength(attack)),
+        (None, Some((character, _))) => character.attack().detached(),
+        (None, None) => return Err(String::from("--attack is required")),
+    };
+    let defense = model(Ability::Stamina(args.required("defense")?));
+    let effect = effect(args, skill.map(|(_, effect)| effect))?;
+    let mut calculator = DamageCalculator::new();
+    for rate in args.all("attack-up")? {
+        calculator.add_attack_up(rate);
+    }
+    for rate in args.all("defense-down")? {
+        calculator.add_defense_down(rate);
+    }
+    if let Some(rate) = args.optional("element-rate")? {
+        calculator.set_element_rate(rate);
+    }
+    if let Some(rate) = args.optional("weapon-rate")? {
+        calculator.set_weapon_rate(rate);
+    }
+    if let Some(multiplier) = args.optional("critical")? {
+        calculator.set_critical(multiplier);
+    }
+    if let Some(rate) = args.optional("destruction")? {
+        calculator.set_destruction(rate);
+    }
+    if let Some(rate) = args.optional("field")? {
+        calculator.set_field(rate);
+    }
+    let base = effect.damage_to_enemy(&attack, &defense);
+    let multiplier = calculator.multiplier();
+    let (low, high) = {
+        let mut calculator = calculator.clone();
+        calculator.set_roll(Some(0.0));
+        let low = calculator.calculate(base);
+        calculator.set_roll(Some(1.0));
+        (low, calculator.calculate(base))
+    };
+    if args.flag("json") {
+        return Ok(json!({
+            "base": base,
+            "multiplier": multiplier,
+            "damage": base * multiplier,
+            "low": low,
+            "high": high,
+        })
+        .to_string());
+    }
+    Ok(format!(
+        "base: {}\nmultiplier: {}\ndamage: {} ({} - {})",
+        base,
+        multiplier,
+        base * multiplier,
+        low,
+        high
+    ))
+}
+
+fn curve(args: &Args) -> Result<String, String> {
+    let effect = effect(args, catalogue_skill(args)?.map(|(_, effect)| effect))?;
+    let from: i32 = args.optional("from")?.unwrap_or(-effect.border());
+    let to: i32 = args.optional("to")?.unwrap_or(effect.border());
+    let step: i32 = args.optional("step")?.unwrap_or(10);
+    if step <= 0 {
+        return Err(String::from("--step must be positive"));
+    }
+    let defense = model(Ability::Stamina(0));
+    let points: Vec<(i32, f32)> = (from..=to)
+        .step_by(step as usize)
+        .map(|sd| {
+            let attack = model(Ability::Strength(sd));
+            let value = if args.flag("enemy") {
+                effect.damage_from_enemy(&attack, &defense)
+            } else {
+                effect.damage_to_enemy(&attack, &defense)
+            };
+            (sd, value)
+        })
+        .collect();
+    if args.flag("json") {
+        let points: Vec<_> = points
+            .iter()
+            .map(|(sd, value)| json!({ "difference": sd, "damage": value }))
+            .collect();
+        return Ok(json!(points).to_string());
+    }
+    Ok(points
+        .iter()
+        .map(|(sd, value)| format!("{}\t{}", sd, value))
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+fn simulate(args: &Args) -> Result<String, String> {
+    let path = args
+        .positional
+        .get(1)
+        .ok_or_else(|| String::from("a battle file is required"))?;
+    let spec = BattleSpec::load(path).map_err(|error| error.to_string())?;
+    let mut battle = spec.battle().map_err(|error| error.to_string())?;
+    let mut rotation = spec.rotation().map_err(|error| error.to_string())?;
+    let turns = args.optional("turns")?.unwrap_or(spec.max_turns);
+    if let Some(runs) = args.optional("runs")? {
+        return sample(
+            &battle,
+            &rotation,
+            turns,
+            runs,
+            args.optional("seed")?.unwrap_or(0),
+            args,
+        );
+    }
+    let log = battle
+        .run(&mut rotation, turns)
+        .map_err(|error| error.to_string())?;
+    if args.flag("json") {
+        return serde_json::to_string(&log).map_err(|error| error.to_string());
+    }
+    Ok(log.to_string().trim_end().to_owned())
+}
+
+fn sample(
+    battle: &Battle,
+    rotation: &ScriptedRotation,
+    turns: u32,
+    runs: usize,
+    seed: u64,
+    args: &Args,
+) -> Result<String, String> {
+    let samples = Sampler::new(runs, seed)
+        .battle(battle, rotation, turns)
+        .map_err(|error| error.to_string())?;
+    let damage = samples.damage().summary();
+    let turns: Vec<(u32, f32, f32)> = (1..=turns)
+        .map(|turn| {
+            (
+                turn,
+                samples.kill_probability(turn),
+                samples.survival_probability(turn),
+            )
+        })
+        .collect();
+    if args.flag("json") {
+        let kills: Vec<_> = turns
+            .iter()
+            .map(|(turn, kill, _)| json!({ "turn": turn, "probability": kill }))
+            .collect();
+        let turns: Vec<_> = turns
+            .iter()
+            .map(|(turn, kill, survival)| {
+                json!({ "turn": turn, "kill": kill, "survival": survival })
+            })
+            .collect();
+        return Ok(json!({ "damage": damage, "kills": kills, "turns": turns }).to_string());
+    }
+    let percent = |probability: f32| (probability * 1000.0).round() / 10.0;
+    let mut output = format!("damage: {}", damage);
+    for (turn, kill, survival) in turns {
+        output.push_str(&format!(
+            "\nturn {}: killed {}%, survived {}%",
+            turn,
+            percent(kill),
+            percent(survival)
+        ));
+    }
+    Ok(output)
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let result = match args.positional.first().map(String::as_str) {
+        Some("damage") => damage(&args),
+        Some("curve") => curve(&args),
+        Some("simulate") => simulate(&args),
+        _ => Err(String::from(USAGE)),
+    };
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::fs;
+
+    fn args(line: &str) -> Args {
+        Args::parse(line.split_whitespace().map(String::from)).expect("it should succeed")
+    }
+
+    fn output(line: &str) -> Value {
+        let output = damage(&args(line)).expect("it should succeed");
+        serde_json::from_str(&output).expect("it should succeed")
+    }
+
+    #[test]
+    fn parse_args() {
+        let parsed = args("damage --attack 300 --json --attack-up 0.1 --attack-up 0.2");
+        assert_eq!(parsed.positional, vec!["damage"]);
+        assert!(parsed.flag("json"));
+        assert!(!parsed.flag("enemy"));
+        assert_eq!(parsed.required::<i32>("attack"), Ok(300));
+        assert_eq!(parsed.all::<f32>("attack-up"), Ok(vec![0.1, 0.2]));
+        assert_eq!(parsed.optional::<f32>("max"), Ok(None));
+        assert!(parsed.required::<i32>("defense").is_err());
+        assert!(args("damage --attack high")
+            .required::<i32>("attack")
+            .is_err());
+        assert!(Args::parse(["damage", "--attack"].into_iter().map(String::from)).is_err());
+    }
+
+    #[test]
+    fn damage_json() {
+        let output = output(
+            "damage --attack 300 --defense 250 --min 1000 --border 100 --attack-up 0.5 --json",
+        );
+        assert_eq!(output["base"], 3000.0);
+        assert_eq!(output["multiplier"], 1.5);
+        assert_eq!(output["damage"], 4500.0);
+    }
+
+    #[test]
+    fn damage_catalogue() {
+        let path = env::temp_dir().join("hbr-damage-catalogue.toml");
+        fs::write(
+            &path,
+            r#"
+[[styles]]
+name = "Ruka"
+abilities = { strength = 300, dexterity = 240, stamina = 200, endurement = 180, luck = 150, intelligence = 120 }
+attack = { kind = "weighted_on_prior", abilities = ["strength", "dexterity"] }
+
+[[styles.skills]]
+name = "Slash"
+
+[[styles.skills.effects]]
+name = "Slash"
+min = 1200.0
+border = 150
+"#,
+        )
+        .expect("it should succeed");
+        let skill = format!(
+            "damage --catalogue {} --skill Slash --defense 250 --json",
+            path.display()
+        );
+        // 280 attack from the style against 250 defense
+        assert_eq!(output(&skill)["base"], 2160.0);
+        assert_eq!(
+            output(&skill)["base"],
+            output("damage --attack 280 --defense 250 --min 1200 --border 150 --json")["base"]
+        );
+        assert_eq!(
+            output(&format!("{} --border 300 --max 2000", skill))["base"],
+            1280.0
+        );
+        assert_eq!(output(&format!("{} --attack 400", skill))["base"], 6000.0);
+        assert!(damage(&args(&skill.replace("Slash", "Thrust"))).is_err());
+        fs::remove_file(&path).expect("it should succeed");
+    }
 }
@@ -0,0 +1,130 @@
+pub const OD_LEVELS: u32 = 3;
+pub const OD_GAUGE_MAX: f32 = 100.0 * OD_LEVELS as f32;
+
+// the party-wide gauge in percent, every full 100 is one level that can be spent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overdrive {
+    gauge: f32,
+    hit_rate: f32,
+    sp_boosts: [i32; OD_LEVELS as usize],
+    turns: u32,
+    running: bool,
+}
+
+impl Default for Overdrive {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl Overdrive {
+    pub fn new(gauge: f32) -> Self {
+        Self {
+            gauge: gauge.clamp(0.0, OD_GAUGE_MAX),
+            hit_rate: 2.5,
+            sp_boosts: [5, 12, 20],
+            turns: 0,
+            running: false,
+        }
+    }
+
+    pub fn gauge(&self) -> f32 {
+        self.gauge
+    }
+
+    pub fn level(&self) -> u32 {
+        (self.gauge / 100.0).floor() as u32
+    }
+
+    pub fn hit_rate(&self) -> f32 {
+        self.hit_rate
+    }
+
+    // gauge percent gained by every hit before the skill rate
+    pub fn set_hit_rate(&mut self, hit_rate: f32) {
+        self.hit_rate = hit_rate;
+    }
+
+    pub fn sp_boost(&self, level: u32) -> i32 {
+        match level {
+            1..=OD_LEVELS => self.sp_boosts[level as usize - 1],
+            _ => 0,
+        }
+    }
+
+    // SP given to the front on activation at OD1, OD2 and OD3
+    pub fn set_sp_boosts(&mut self, sp_boosts: [i32; OD_LEVELS as usize]) {
+        self.sp_boosts = sp_boosts;
+    }
+
+    // overdrive turns still to be played
+    pub fn turns(&self) -> u32 {
+        self.turns
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // hits inside an overdrive turn do not fill the gauge, returns the gain
+    pub fn fill(&mut self, hits: usize, rate: f32) -> f32 {
+        if self.running {
+            return 0.0;
+        }
+        let before = self.gauge;
+        self.gauge = (self.gauge + hits as f32 * self.hit_rate * rate).clamp(0.0, OD_GAUGE_MAX);
+        self.gauge - before
+    }
+
+    // spends level * 100 for as many extra turns, None when the gauge is short
+    // or an overdrive is still running or pending
+    pub fn activate(&mut self, level: u32) -> Option<i32> {
+        if self.running || self.turns > 0 || level == 0 || level > self.level() {
+            return None;
+        }
+        self.gauge -= 100.0 * level as f32;
+        self.turns += level;
+        Some(self.sp_boost(level))
+    }
+
+    // starts the next overdrive turn, false once they are all played
+    pub fn next_turn(&mut self) -> bool {
+        self.running = self.turns > 0;
+        if self.running {
+            self.turns -= 1;
+        }
+        self.running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_and_activate() {
+        let mut overdrive = Overdrive::new(90.0);
+        assert_eq!(overdrive.activate(1), None);
+        assert_eq!(overdrive.fill(6, 2.0), 30.0);
+        assert_eq!(overdrive.level(), 1);
+        assert_eq!(overdrive.activate(2), None);
+        assert_eq!(overdrive.activate(1), Some(5));
+        assert_eq!(overdrive.gauge(), 20.0);
+        assert!(overdrive.next_turn());
+        assert_eq!(overdrive.fill(10, 1.0), 0.0);
+        assert!(!overdrive.next_turn());
+        overdrive.fill(1000, 1.0);
+        assert_eq!(overdrive.gauge(), OD_GAUGE_MAX);
+        assert_eq!(overdrive.activate(3), Some(20));
+        assert_eq!(overdrive.turns(), 3);
+
+        // no activation on top of an overdrive
+        let mut overdrive = Overdrive::new(OD_GAUGE_MAX);
+        assert_eq!(overdrive.activate(1), Some(5));
+        assert_eq!(overdrive.activate(1), None);
+        assert!(overdrive.next_turn());
+        assert_eq!(overdrive.activate(1), None);
+        assert!(!overdrive.next_turn());
+        assert_eq!(overdrive.activate(1), Some(5));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::character::Character;
+use crate::skill::{BreakCondition, Skill};
+use crate::target::Target;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    BattleStart,
+    TurnStart,
+    Overdrive,
+    // once HP falls under the rate of the maximum
+    HpBelow(f32),
+    Critical,
+    Break,
+    SwapIn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Front,
+    Back,
+    SpAtLeast(i32),
+    Enemy(BreakCondition),
+}
+
+// a skill that is never chosen, the battle uses it for free when the trigger fires
+#[derive(Debug, Clone)]
+pub struct Passive {
+    trigger: Trigger,
+    conditions: Vec<Condition>,
+    skill: Skill,
+    limit: Option<u32>,
+    fired: u32,
+}
+
+impl Trigger {
+    // whether going from before to after falls through the threshold, false for the others
+    pub fn crossed(&self, before: f32, after: f32) -> bool {
+        match *self {
+            Self::HpBelow(rate) => before >= rate && after < rate,
+            _ => false,
+        }
+    }
+}
+
+impl Condition {
+    pub fn matches(&self, member: &Character, front: bool, enemy: Option<&Target>) -> bool {
+        match *self {
+            Self::Front => front,
+            Self::Back => !front,
+            Self::SpAtLeast(sp) => member.sp().current() >= sp,
+            Self::Enemy(condition) => {
+                enemy.is_some_and(|enemy| condition.matches(enemy.is_broken()))
+            }
+        }
+    }
+}
+
+impl Passive {
+    pub fn new(trigger: Trigger, skill: Skill) -> Self {
+        Self {
+            trigger,
+            conditions: vec![],
+            skill,
+            limit: None,
+            fired: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.skill.name()
+    }
+
+    pub fn trigger(&self) -> Trigger {
+        self.trigger
+    }
+
+    pub fn skill(&self) -> &Skill {
+        &self.skill
+    }
+
+    // every condition has to hold when the trigger fires
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    // times the passive can fire in a battle, no limit by default
+    pub fn set_limit(&mut self, limit: Option<u32>) {
+        self.limit = limit;
+    }
+
+    pub fn fired(&self) -> u32 {
+        self.fired
+    }
+
+    pub fn is_ready(&self, member: &Character, front: bool, enemy: Option<&Target>) -> bool {
+        self.limit.is_none_or(|limit| self.fired < limit)
+            && self
+                .conditions
+                .iter()
+                .all(|condition| condition.matches(member, front, enemy))
+    }
+
+    pub fn fire(&mut self) {
+        self.fired += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::Formula;
+    use crate::sp::SpPool;
+
+    #[test]
+    fn conditions_and_limit() {
+        let mut member = Character::new("a", [100; 6], &Formula::default());
+        member.set_sp(SpPool::new(8, 20, 2));
+        let mut passive = Passive::new(Trigger::HpBelow(0.5), Skill::new("last stand"));
+        passive.add_condition(Condition::Front);
+        passive.add_condition(Condition::SpAtLeast(8));
+        passive.set_limit(Some(1));
+        let enemy = Target::new("enemy", 0.0, 100.0);
+        assert!(!passive.is_ready(&member, false, Some(&enemy)));
+        assert!(passive.is_ready(&member, true, Some(&enemy)));
+        passive.add_condition(Condition::Enemy(BreakCondition::Broken));
+        assert!(passive.is_ready(&member, true, Some(&enemy)));
+        passive.fire();
+        assert!(!passive.is_ready(&member, true, Some(&enemy)));
+        assert!(passive.trigger().crossed(0.6, 0.4));
+        assert!(!passive.trigger().crossed(0.4, 0.3));
+    }
+}
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+// which front-row members an enemy action goes after
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Targeting {
+    Slot(usize),
+    Random,
+    LowestHp,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnemyAction {
+    pub skill: usize,
+    pub targeting: Targeting,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnCondition {
+    On(u32),
+    Every(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Phase {
+    hp_rate: f32,
+    cycle: Vec<Vec<EnemyAction>>,
+}
+
+// cycles through the turns of the current phase, a turn trigger takes the turn over
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pattern {
+    phases: Vec<Phase>,
+    triggers: Vec<(TurnCondition, Vec<EnemyAction>)>,
+    phase: usize,
+    position: usize,
+}
+
+impl EnemyAction {
+    pub fn new(skill: usize, targeting: Targeting) -> Self {
+        Self { skill, targeting }
+    }
+}
+
+impl TurnCondition {
+    pub fn matches(&self, turn: u32) -> bool {
+        match *self {
+            Self::On(on) => turn == on,
+            Self::Every(every) => every > 0 && turn.is_multiple_of(every),
+        }
+    }
+}
+
+impl Pattern {
+    // the opening phase, played from full HP
+    pub fn new(cycle: Vec<Vec<EnemyAction>>) -> Self {
+        Self {
+            phases: vec![Phase {
+                hp_rate: 1.0,
+                cycle,
+            }],
+            triggers: vec![],
+            phase: 0,
+            position: 0,
+        }
+    }
+
+    // entered once HP falls to hp_rate of the maximum, phases go in falling order
+    pub fn add_phase(&mut self, hp_rate: f32, cycle: Vec<Vec<EnemyAction>>) {
+        self.phases.push(Phase { hp_rate, cycle });
+    }
+
+    pub fn add_trigger(&mut self, condition: TurnCondition, actions: Vec<EnemyAction>) {
+        self.triggers.push((condition, actions));
+    }
+
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    // every skill index the pattern can use
+    pub fn skills(&self) -> impl Iterator<Item = usize> + '_ {
+        self.phases
+            .iter()
+            .flat_map(|phase| phase.cycle.iter().flatten())
+            .chain(self.triggers.iter().flat_map(|(_, actions)| actions))
+            .map(|action| action.skill)
+    }
+
+    // phases never go back, not even after the enemy heals
+    pub fn actions(&mut self, turn: u32, hp_rate: f32) -> Vec<EnemyAction> {
+        while self
+            .phases
+            .get(self.phase + 1)
+            .is_some_and(|next| hp_rate <= next.hp_rate)
+        {
+            self.phase += 1;
+            self.position = 0;
+        }
+        if let Some((_, actions)) = self
+            .triggers
+            .iter()
+            .find(|(condition, _)| condition.matches(turn))
+        {
+            return actions.clone();
+        }
+        let Some(phase) = self.phases.get(self.phase) else {
+            return vec![];
+        };
+        if phase.cycle.is_empty() {
+            return vec![];
+        }
+        let actions = phase.cycle[self.position % phase.cycle.len()].clone();
+        self.position += 1;
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phases_and_triggers() {
+        let action = |skill| vec![EnemyAction::new(skill, Targeting::Random)];
+        let mut pattern = Pattern::new(vec![action(0), action(1)]);
+        pattern.add_phase(0.5, vec![action(2)]);
+        pattern.add_trigger(TurnCondition::Every(3), action(3));
+        let played: Vec<usize> = [(1, 1.0), (2, 1.0), (3, 1.0), (4, 1.0), (5, 0.4)]
+            .into_iter()
+            .chain([(6, 0.9), (7, 0.9)])
+            .map(|(turn, hp_rate)| pattern.actions(turn, hp_rate)[0].skill)
+            .collect();
+        assert_eq!(played, vec![0, 1, 3, 0, 2, 3, 2]);
+        assert_eq!(pattern.phase(), 1);
+    }
+}
@@ -0,0 +1,355 @@
+use crate::battle::{Battle, BattleError, Rotation};
+use crate::character::Character;
+use crate::critical::Critical;
+use crate::skill::{DamageCalculator, Skill};
+use crate::target::Enemy;
+use serde::Serialize;
+use std::fmt::{self, Display, Formatter};
+use std::thread;
+
+// SplitMix64, small and the same on every platform so a seed always replays the same rolls
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+// sorted samples of one measurement
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    samples: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub runs: usize,
+    pub mean: f32,
+    pub min: f32,
+    pub p10: f32,
+    pub p50: f32,
+    pub p90: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BattleSamples {
+    kill_turns: Vec<Option<u32>>,
+    wipe_turns: Vec<Option<u32>>,
+    damage: Distribution,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    runs: usize,
+    seed: u64,
+    threads: usize,
+}
+
+const GOLDEN: u64 = 0x9e37_79b9_7f4a_7c15;
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // the run-th stream of a seed, so results do not depend on how runs are split over threads
+    pub fn stream(seed: u64, run: u64) -> Self {
+        let mut rng = Self::new(seed ^ run.wrapping_mul(GOLDEN).rotate_left(32));
+        rng.state = rng.next_u64();
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // both ends inclusive
+    pub fn range(&mut self, low: u32, high: u32) -> u32 {
+        if high <= low {
+            return low;
+        }
+        low + (self.next_u64() % (u64::from(high - low) + 1)) as u32
+    }
+}
+
+impl Distribution {
+    // None without samples
+    pub fn new(mut samples: Vec<f32>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        samples.sort_by(f32::total_cmp);
+        Some(Self { samples })
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn mean(&self) -> f32 {
+        self.samples.iter().sum::<f32>() / self.samples.len() as f32
+    }
+
+    pub fn min(&self) -> f32 {
+        self.samples[0]
+    }
+
+    pub fn max(&self) -> f32 {
+        self.samples[self.samples.len() - 1]
+    }
+
+    // nearest rank, percentile in [0, 100]
+    pub fn percentile(&self, percentile: f32) -> f32 {
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * self.samples.len() as f32).ceil();
+        self.samples[(rank as usize).max(1) - 1]
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            runs: self.samples.len(),
+            mean: self.mean(),
+            min: self.min(),
+            p10: self.percentile(10.0),
+            p50: self.percentile(50.0),
+            p90: self.percentile(90.0),
+            max: self.max(),
+        }
+    }
+}
+
+impl BattleSamples {
+    pub fn kill_turns(&self) -> &[Option<u32>] {
+        &self.kill_turns
+    }
+
+    // total damage dealt in each run
+    pub fn damage(&self) -> &Distribution {
+        &self.damage
+    }
+
+    pub fn kill_probability(&self, turns: u32) -> f32 {
+        let killed = self
+            .kill_turns
+            .iter()
+            .filter(|kill_turn| kill_turn.is_some_and(|turn| turn <= turns))
+            .count();
+        killed as f32 / self.kill_turns.len() as f32
+    }
+
+    pub fn wipe_turns(&self) -> &[Option<u32>] {
+        &self.wipe_turns
+    }
+
+    // the share of runs where the party still stands after the turns
+    pub fn survival_probability(&self, turns: u32) -> f32 {
+        let wiped = self
+            .wipe_turns
+            .iter()
+            .filter(|wipe_turn| wipe_turn.is_some_and(|turn| turn <= turns))
+            .count();
+        1.0 - wiped as f32 / self.wipe_turns.len() as f32
+    }
+
+    // None when no run got the kill
+    pub fn kill_turn(&self) -> Option<Distribution> {
+        Distribution::new(
+            self.kill_turns
+                .iter()
+                .flatten()
+                .map(|&turn| turn as f32)
+                .collect(),
+        )
+    }
+}
+
+impl Sampler {
+    pub fn new(runs: usize, seed: u64) -> Self {
+        Self {
+            runs: runs.max(1),
+            seed,
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    // runs f for every run index, spread over the threads, in run order
+    fn each<T, F>(&self, f: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(u64) -> T + Sync,
+    {
+        let chunk = self.runs.div_ceil(self.threads);
+        let f = &f;
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.runs)
+                .step_by(chunk)
+                .map(|start| {
+                    let end = (start + chunk).min(self.runs);
+                    scope.spawn(move || (start..end).map(|run| f(run as u64)).collect::<Vec<T>>())
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("a sampling thread panicked"))
+                .collect()
+        })
+    }
+
+    // damage of a single use of the skill against the enemy as it stands
+    pub fn skill(
+        &self,
+        character: &Character,
+        skill: &Skill,
+        enemy: &Enemy,
+        calculator: &DamageCalculator,
+        critical: &Critical,
+    ) -> Distribution {
+        let samples = self.each(|run| {
+            let mut rng = Rng::stream(self.seed, run);
+            character.roll_damage(skill, enemy, calculator, critical, Some(&mut rng))
+        });
+        Distribution::new(samples).expect("there is at least one run")
+    }
+
+    pub fn battle<R>(
+        &self,
+        battle: &Battle,
+        rotation: &R,
+        max_turns: u32,
+    ) -> Result<BattleSamples, BattleError>
+    where
+        R: Rotation + Clone + Send + Sync,
+    {
+        let logs = self.each(|run| {
+            let mut battle = battle.clone();
+            let mut rotation = rotation.clone();
+            battle.set_rng(Some(Rng::stream(self.seed, run)));
+            battle.run(&mut rotation, max_turns)
+        });
+        let mut kill_turns = vec![];
+        let mut wipe_turns = vec![];
+        let mut damage = vec![];
+        for log in logs {
+            let log = log?;
+            kill_turns.push(log.kill_turn);
+            wipe_turns.push(log.wipe_turn);
+            damage.push(log.damage());
+        }
+        Ok(BattleSamples {
+            kill_turns,
+            wipe_turns,
+            damage: Distribution::new(damage).expect("there is at least one run"),
+        })
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "mean {} over {} runs, min {}, p10 {}, p50 {}, p90 {}, max {}",
+            self.mean.round(),
+            self.runs,
+            self.min.round(),
+            self.p10.round(),
+            self.p50.round(),
+            self.p90.round(),
+            self.max.round()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::battle::{Action, ScriptedRotation};
+    use crate::testing::{enemy, member};
+
+    #[test]
+    fn seeded_rolls() {
+        let mut rng = Rng::stream(7, 3);
+        let rolls: Vec<f32> = (0..1000).map(|_| rng.next_f32()).collect();
+        assert_eq!(
+            rolls,
+            (0..1000)
+                .scan(Rng::stream(7, 3), |rng, _| Some(rng.next_f32()))
+                .collect::<Vec<f32>>()
+        );
+        assert!(rolls.iter().all(|roll| (0.0..1.0).contains(roll)));
+        assert_ne!(Rng::stream(7, 3), Rng::stream(7, 4));
+        let distribution = Distribution::new(vec![4.0, 1.0, 3.0, 2.0]).expect("it should exist");
+        assert_eq!(distribution.mean(), 2.5);
+        assert_eq!(distribution.percentile(50.0), 2.0);
+        assert_eq!(distribution.percentile(90.0), 4.0);
+        assert_eq!(distribution.min(), 1.0);
+        assert!(Distribution::new(vec![]).is_none());
+    }
+
+    #[test]
+    fn skill_and_battle_samples() {
+        let character = member("a", 200);
+        let skill = character.skill("attack").expect("it should exist");
+        let target = enemy(0.0, 1_000_000.0);
+        let mut critical = Critical::new();
+        critical.set_base_rate(0.5, 0.0);
+        let mut sampler = Sampler::new(2000, 42);
+        let damage = sampler.skill(
+            &character,
+            skill,
+            &target,
+            &DamageCalculator::new(),
+            &critical,
+        );
+        // 5000 spread over 0.9 - 1.1, half of the hits at 1.5x
+        assert!(damage.min() >= 4500.0 && damage.max() <= 8250.0);
+        assert!((damage.mean() / 6250.0 - 1.0).abs() < 0.02);
+        sampler.set_threads(1);
+        assert_eq!(
+            sampler
+                .skill(
+                    &character,
+                    skill,
+                    &target,
+                    &DamageCalculator::new(),
+                    &critical
+                )
+                .samples(),
+            damage.samples()
+        );
+
+        // any critical clears 10400 HP in two turns, two plain hits need a high spread
+        let mut battle = Battle::new(vec![member("a", 200)], vec![enemy(0.0, 10400.0)])
+            .expect("it should succeed");
+        battle.set_critical(critical);
+        let mut rotation = ScriptedRotation::new();
+        rotation.add_turn(vec![Action::skill(0, 0, 0)]);
+        let samples = Sampler::new(1000, 42)
+            .battle(&battle, &rotation, 5)
+            .expect("it should succeed");
+        let early = samples.kill_probability(2);
+        assert!(early > 0.6 && early < 0.9);
+        assert_eq!(samples.kill_probability(3), 1.0);
+        assert_eq!(samples.kill_probability(1), 0.0);
+        assert_eq!(samples.kill_turn().expect("it should exist").max(), 3.0);
+        assert_eq!(samples.survival_probability(5), 1.0);
+    }
+}
@@ -1,25 +1,116 @@
-use crate::ability::AbilityModel;
+use crate::ability::{AbilityModel, AbilityName, ModelFormula};
+use crate::buff::BuffStat;
+use crate::element::{AttackType, Element};
+use crate::field::{Layer, Zone};
+use crate::sampling::Rng;
+use crate::targets::{EffectRecord, Targets};
+use serde::{Deserialize, Serialize};
 use std::fmt::{self, Display, Formatter};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Pool {
+    Dp,
+    Hp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    Damage,
+    Sp,
+    SpCostDown,
+    Buff(BuffStat),
+    AbilityUp(AbilityName),
+    AbilityDown(AbilityName),
+    Heal(Pool),
+    // lays a zone on the battlefield, for the element of the effect or every element
+    Zone(Layer),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Actor,
+    Ally,
+    AllAllies,
+    Enemy,
+    AllEnemies,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakCondition {
+    Broken,
+    Unbroken,
+}
+
+#[derive(Debug, Clone)]
 pub struct SkillEffect {
+    kind: EffectKind,
+    scope: Scope,
     name: String,
     min: f32,
     max: f32,
     border: i32,
+    dp_rate: f32,
+    hp_rate: f32,
+    destruction: f32,
+    element: Element,
+    attack_type: Option<AttackType>,
+    duration: Option<u32>,
+    hits: u32,
+    max_hits: u32,
+    hit_weights: Vec<f32>,
+    model: Option<ModelFormula>,
+    overheal: f32,
+    break_bonus: f32,
+    condition: Option<BreakCondition>,
 }
 
+#[derive(Debug, Clone)]
 pub struct Skill {
     name: String,
+    sp_cost: i32,
+    od_rate: f32,
     effects: Vec<SkillEffect>,
 }
 
+#[derive(Debug, Clone)]
+pub struct DamageCalculator {
+    attack_up: Vec<f32>,
+    defense_down: Vec<f32>,
+    element_rate: f32,
+    weapon_rate: f32,
+    critical: f32,
+    destruction: f32,
+    field: f32,
+    spread: (f32, f32),
+    roll: Option<f32>,
+}
+
 impl SkillEffect {
     pub fn new(name: &str, min: f32, max: f32, border: i32) -> Self {
         Self {
+            kind: EffectKind::Damage,
+            scope: Scope::Enemy,
             name: name.to_owned(),
             min,
             max,
             border,
+            dp_rate: 1.0,
+            hp_rate: 1.0,
+            destruction: 0.0,
+            element: Element::None,
+            attack_type: None,
+            duration: None,
+            hits: 1,
+            max_hits: 1,
+            hit_weights: vec![],
+            model: None,
+            overheal: 1.0,
+            break_bonus: 1.0,
+            condition: None,
         }
     }
 
@@ -31,6 +122,83 @@ impl SkillEffect {
         Self::new(name, min, min * 3.0, border)
     }
 
+    // restores the pool of an ally, scaled by the healer's heal model
+    pub fn new_heal(name: &str, pool: Pool, min: f32, border: i32) -> Self {
+        let mut effect = Self::new_recover(name, min, border);
+        effect.kind = EffectKind::Heal(pool);
+        effect.scope = Scope::Ally;
+        effect
+    }
+
+    pub fn new_sp(name: &str, amount: i32) -> Self {
+        let mut effect = Self::new(name, amount as f32, amount as f32, 0);
+        effect.kind = EffectKind::Sp;
+        effect.scope = Scope::Actor;
+        effect
+    }
+
+    pub fn new_sp_cost_down(name: &str, amount: i32, turns: u32) -> Self {
+        let mut effect = Self::new(name, amount as f32, amount as f32, 0);
+        effect.kind = EffectKind::SpCostDown;
+        effect.scope = Scope::Actor;
+        effect.duration = Some(turns);
+        effect
+    }
+
+    // min at 0 up to max at the border of the caster's model, debuffs go to the enemy
+    pub fn new_buff(name: &str, kind: EffectKind, min: f32, max: f32, border: i32) -> Self {
+        let mut effect = Self::new(name, min, max, border);
+        effect.kind = kind;
+        effect.scope = match kind {
+            EffectKind::Buff(BuffStat::DefenseDown | BuffStat::ElementDefenseDown(_)) => {
+                Scope::Enemy
+            }
+            _ => Scope::Actor,
+        };
+        effect
+    }
+
+    // the element comes from set_element, a zone without one boosts every element
+    pub fn new_zone(name: &str, layer: Layer, min: f32, max: f32, border: i32) -> Self {
+        let mut effect = Self::new(name, min, max, border);
+        effect.kind = EffectKind::Zone(layer);
+        effect.scope = Scope::Actor;
+        effect
+    }
+
+    // the zone this effect lays at the rate
+    pub fn zone(&self, rate: f32) -> Zone {
+        let element = match self.element {
+            Element::None => None,
+            element => Some(element),
+        };
+        Zone::new(&self.name, element, rate, self.duration)
+    }
+
+    pub fn kind(&self) -> EffectKind {
+        self.kind
+    }
+
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+
+    pub fn duration(&self) -> Option<u32> {
+        self.duration
+    }
+
+    pub fn set_kind(&mut self, kind: EffectKind) {
+        self.kind = kind;
+    }
+
+    pub fn set_scope(&mut self, scope: Scope) {
+        self.scope = scope;
+    }
+
+    pub fn set_duration(&mut self, duration: Option<u32>) {
+        self.duration = duration;
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -47,30 +215,157 @@ impl SkillEffect {
         self.border
     }
 
+    pub fn dp_rate(&self) -> f32 {
+        self.dp_rate
+    }
+
+    pub fn hp_rate(&self) -> f32 {
+        self.hp_rate
+    }
+
+    pub fn destruction(&self) -> f32 {
+        self.destruction
+    }
+
+    pub fn element(&self) -> Element {
+        self.element
+    }
+
+    pub fn attack_type(&self) -> Option<AttackType> {
+        self.attack_type
+    }
+
+    pub fn set_element(&mut self, element: Element) {
+        self.element = element;
+    }
+
+    pub fn set_attack_type(&mut self, attack_type: Option<AttackType>) {
+        self.attack_type = attack_type;
+    }
+
     pub fn reset_damage(&mut self, min: f32, max: f32) {
         self.min = min;
         self.max = max;
     }
 
+    pub fn set_border(&mut self, border: i32) {
+        self.border = border;
+    }
+
+    pub fn set_rates(&mut self, dp_rate: f32, hp_rate: f32) {
+        self.dp_rate = dp_rate;
+        self.hp_rate = hp_rate;
+    }
+
+    // added to the target's destruction rate for every hit landing on HP
+    pub fn set_destruction(&mut self, coefficient: f32) {
+        self.destruction = coefficient;
+    }
+
+    pub fn model(&self) -> Option<&ModelFormula> {
+        self.model.as_ref()
+    }
+
+    // the caster's model scaling a support effect, the attack model when None
+    pub fn set_model(&mut self, model: Option<ModelFormula>) {
+        self.model = model;
+    }
+
+    pub fn overheal(&self) -> f32 {
+        self.overheal
+    }
+
+    // heals fill up to this many times the maximum
+    pub fn set_overheal(&mut self, cap: f32) {
+        self.overheal = cap;
+    }
+
+    pub fn break_bonus(&self) -> f32 {
+        self.break_bonus
+    }
+
+    // damage multiplier for hits on a target that is already broken
+    pub fn set_break_bonus(&mut self, rate: f32) {
+        self.break_bonus = rate;
+    }
+
+    pub fn condition(&self) -> Option<BreakCondition> {
+        self.condition
+    }
+
+    // the effect is skipped unless the targeted enemy is in this state
+    pub fn set_condition(&mut self, condition: Option<BreakCondition>) {
+        self.condition = condition;
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn max_hits(&self) -> u32 {
+        self.max_hits
+    }
+
+    pub fn hit_weights(&self) -> &[f32] {
+        &self.hit_weights
+    }
+
+    pub fn set_hits(&mut self, hits: u32) {
+        self.set_hit_range(hits, hits);
+    }
+
+    // a random count of hits between min and max
+    pub fn set_hit_range(&mut self, min: u32, max: u32) {
+        self.hits = min.max(1);
+        self.max_hits = max.max(self.hits);
+    }
+
+    // share of the damage for each hit, only used when the count of hits matches
+    pub fn set_hit_weights(&mut self, weights: Vec<f32>) {
+        self.hit_weights = weights;
+    }
+
+    // rolled from rng, the middle of the range without one
+    pub fn roll_hits(&self, rng: Option<&mut Rng>) -> u32 {
+        match rng {
+            Some(rng) => rng.range(self.hits, self.max_hits),
+            None => (self.hits + self.max_hits) / 2,
+        }
+    }
+
+    // the fraction of the damage each of count hits deals, an even split by default
+    pub fn hit_shares(&self, count: u32) -> Vec<f32> {
+        let count = count.max(1);
+        let total: f32 = self.hit_weights.iter().sum();
+        if self.hit_weights.len() == count as usize && total > 0.0 {
+            return self
+                .hit_weights
+                .iter()
+                .map(|weight| weight / total)
+                .collect();
+        }
+        vec![1.0 / count as f32; count as usize]
+    }
+
     fn cause_damage(&self, sd: i32, border_factor: f32) -> f32 {
-        let min_threshold = (-1.0) * self.border as f32 * border_factor;
+        let min_threshold = -(self.border as f32 * border_factor);
         if sd >= self.border {
-            return self.max;
+            self.max
         } else if sd as f32 <= min_threshold {
-            return 1.0;
+            1.0
         } else if sd >= 0 {
-            return self.min + (self.max - self.min) / self.border as f32 * sd as f32;
+            self.min + (self.max - self.min) / self.border as f32 * sd as f32
         } else {
-            return 1.0 + (self.min - 1.0) / min_threshold * sd as f32;
+            1.0 + (self.min - 1.0) / min_threshold * sd as f32
         }
     }
     fn cause_effect(&self, sd: i32) -> f32 {
         if sd >= self.border {
-            return self.max;
+            self.max
         } else if sd <= 0 {
-            return self.min;
+            self.min
         } else {
-            return self.min + (self.max - self.min) / self.border as f32 * sd as f32;
+            self.min + (self.max - self.min) / self.border as f32 * sd as f32
         }
     }
 
@@ -104,10 +399,29 @@ impl Skill {
     pub fn new(name: &str) -> Self {
         Self {
             name: name.to_owned(),
+            sp_cost: 0,
+            od_rate: 1.0,
             effects: vec![],
         }
     }
 
+    pub fn sp_cost(&self) -> i32 {
+        self.sp_cost
+    }
+
+    pub fn set_sp_cost(&mut self, sp_cost: i32) {
+        self.sp_cost = sp_cost;
+    }
+
+    pub fn od_rate(&self) -> f32 {
+        self.od_rate
+    }
+
+    // scales the overdrive gauge gained by each hit of the skill
+    pub fn set_od_rate(&mut self, od_rate: f32) {
+        self.od_rate = od_rate;
+    }
+
     pub fn add_effect(&mut self, effect: SkillEffect) {
         self.effects.push(effect);
     }
@@ -115,6 +429,151 @@ impl Skill {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    pub fn effects(&self) -> std::slice::Iter<'_, SkillEffect> {
+        self.effects.iter()
+    }
+
+    // whether any effect lands on the enemy side
+    pub fn targets_enemy(&self) -> bool {
+        self.effects.iter().any(|effect| effect.scope().is_enemy())
+    }
+
+    pub fn effects_mut(&mut self) -> std::slice::IterMut<'_, SkillEffect> {
+        self.effects.iter_mut()
+    }
+
+    // applies the effects in order, SP is not paid here,
+    // None when the actor is not in the party
+    pub fn execute(&self, actor: usize, targets: &mut Targets) -> Option<Vec<EffectRecord>> {
+        targets.execute(actor, self)
+    }
+}
+
+impl Scope {
+    pub fn is_enemy(&self) -> bool {
+        matches!(self, Self::Enemy | Self::AllEnemies)
+    }
+}
+
+impl BreakCondition {
+    pub fn matches(&self, broken: bool) -> bool {
+        match self {
+            Self::Broken => broken,
+            Self::Unbroken => !broken,
+        }
+    }
+}
+
+impl Default for DamageCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DamageCalculator {
+    pub fn new() -> Self {
+        Self {
+            attack_up: vec![],
+            defense_down: vec![],
+            element_rate: 1.0,
+            weapon_rate: 1.0,
+            critical: 1.0,
+            destruction: 1.0,
+            field: 0.0,
+            spread: (0.9, 1.1),
+            roll: None,
+        }
+    }
+
+    // rates are fractions: 0.3 is +30%
+    pub fn add_attack_up(&mut self, rate: f32) {
+        self.attack_up.push(rate);
+    }
+
+    pub fn add_defense_down(&mut self, rate: f32) {
+        self.defense_down.push(rate.abs());
+    }
+
+    pub fn set_element_rate(&mut self, rate: f32) {
+        self.element_rate = rate;
+    }
+
+    pub fn set_weapon_rate(&mut self, rate: f32) {
+        self.weapon_rate = rate;
+    }
+
+    pub fn set_critical(&mut self, multiplier: f32) {
+        self.critical = multiplier;
+    }
+
+    // destruction rate as a multiplier: 1.0 is 100%
+    pub fn set_destruction(&mut self, rate: f32) {
+        self.destruction = rate;
+    }
+
+    pub fn set_field(&mut self, rate: f32) {
+        self.field = rate;
+    }
+
+    // stacks on top of the field rate already set
+    pub fn add_field(&mut self, rate: f32) {
+        self.field += rate;
+    }
+
+    pub fn set_spread(&mut self, low: f32, high: f32) {
+        self.spread = (low, high);
+    }
+
+    // roll in [0, 1) picks a point in the spread, None uses the midpoint
+    pub fn set_roll(&mut self, roll: Option<f32>) {
+        self.roll = roll;
+    }
+
+    pub fn destruction(&self) -> f32 {
+        self.destruction
+    }
+
+    pub fn spread_factor(&self) -> f32 {
+        let (low, high) = self.spread;
+        match self.roll {
+            Some(roll) => low + (high - low) * roll.clamp(0.0, 1.0),
+            None => (low + high) / 2.0,
+        }
+    }
+
+    pub fn multiplier(&self) -> f32 {
+        (1.0 + self.attack_up.iter().sum::<f32>())
+            * (1.0 + self.defense_down.iter().sum::<f32>())
+            * self.element_rate
+            * self.weapon_rate
+            * self.critical
+            * self.destruction
+            * (1.0 + self.field)
+            * self.spread_factor()
+    }
+
+    pub fn calculate(&self, base: f32) -> f32 {
+        base * self.multiplier()
+    }
+
+    pub fn damage(
+        &self,
+        effect: &SkillEffect,
+        attacker_model: &AbilityModel,
+        defender_model: &AbilityModel,
+    ) -> f32 {
+        self.calculate(effect.damage_to_enemy(attacker_model, defender_model))
+    }
+}
+
+impl Display for Pool {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Dp => write!(f, "DP"),
+            Self::Hp => write!(f, "HP"),
+        }
+    }
 }
 
 impl Display for SkillEffect {
@@ -123,7 +582,14 @@ impl Display for SkillEffect {
             f,
             "{}: {}-{}/{}",
             self.name, self.min, self.max, self.border
-        )
+        )?;
+        if self.element != Element::None {
+            write!(f, " {}", self.element)?;
+        }
+        match self.attack_type {
+            Some(attack_type) => write!(f, " {}", attack_type),
+            None => Ok(()),
+        }
     }
 }
 
@@ -131,8 +597,9 @@ impl Display for Skill {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
             f,
-            "[{}] Effects:[{}]",
+            "[{}] SP {} Effects:[{}]",
             self.name,
+            self.sp_cost,
             self.effects
                 .iter()
                 .map(|e| e.to_string())
@@ -146,6 +613,10 @@ impl Display for Skill {
 mod tests {
     use super::*;
     use crate::ability::*;
+    use crate::character::{Character, Formula};
+    use crate::critical::Critical;
+    use crate::targets::Outcome;
+    use crate::testing::{enemy, single};
 
     #[test]
     fn cause_damage() {
@@ -157,7 +628,7 @@ mod tests {
             AbilityModel::new(AbilityModelType::Single, attacker_ability.get_cell(), None)
                 .expect("it should succeed");
         let defender_model =
-            AbilityModel::new(AbilityModelType::Equal, defender_ability.get_cell(), None)
+            AbilityModel::new(AbilityModelType::Single, defender_ability.get_cell(), None)
                 .expect("it should succeed");
         let skill_effect = SkillEffect::new_damage("damage1", 2000.0, 150);
         // too low (100 to 300)
@@ -172,4 +643,73 @@ mod tests {
         //weak from
         defender_ability.get_mut().apply_positive(100);
     }
+
+    #[test]
+    fn damage_pipeline() {
+        let attacker_model = single(Ability::Strength(400));
+        let defender_model = single(Ability::Stamina(250));
+        let skill_effect = SkillEffect::new_damage("damage1", 1000.0, 150);
+        let mut calculator = DamageCalculator::new();
+        // at the border, no modifiers
+        assert_eq!(
+            calculator.damage(&skill_effect, &attacker_model, &defender_model),
+            5000.0
+        );
+        calculator.add_attack_up(0.3);
+        calculator.add_attack_up(0.2);
+        calculator.add_defense_down(-0.5);
+        calculator.set_element_rate(2.0);
+        calculator.set_critical(1.5);
+        calculator.set_destruction(1.2);
+        assert_eq!(
+            calculator.calculate(100.0),
+            100.0 * 1.5 * 1.5 * 2.0 * 1.5 * 1.2
+        );
+        calculator.set_roll(Some(0.0));
+        assert_eq!(calculator.spread_factor(), 0.9);
+        calculator.set_roll(Some(1.0));
+        assert_eq!(calculator.spread_factor(), 1.1);
+    }
+
+    #[test]
+    fn execute_skill() {
+        let formula = Formula {
+            attack: ModelFormula::single(AbilityName::Strength),
+            ..Formula::default()
+        };
+        let mut skill = Skill::new("double");
+        let mut damage = SkillEffect::new_damage("double", 1000.0, 100);
+        damage.set_hits(2);
+        skill.add_effect(damage);
+        skill.add_effect(SkillEffect::new_sp("double", 3));
+        assert_eq!(skill.effects().map(SkillEffect::hits).sum::<u32>(), 3);
+        let mut party = vec![Character::new("a", [200, 0, 0, 0, 0, 0], &formula)];
+        let mut enemies = vec![enemy(0.0, 100000.0)];
+        let mut targets = Targets::new(&mut party, &mut enemies);
+        targets.set_critical({
+            let mut critical = Critical::new();
+            critical.set_base_rate(0.0, 0.0);
+            critical
+        });
+        assert!(skill.execute(1, &mut targets).is_none());
+        let records = skill.execute(0, &mut targets).expect("it should succeed");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, EffectKind::Damage);
+        match &records[0].outcomes[..] {
+            [Outcome::Damage { hits, .. }] => {
+                assert_eq!(hits.len(), 2);
+                assert_eq!(hits[0].hp_damage + hits[1].hp_damage, 5000.0);
+            }
+            outcomes => panic!("unexpected outcomes {:?}", outcomes),
+        }
+        assert_eq!(
+            records[1].outcomes,
+            vec![Outcome::Sp {
+                target: String::from("a"),
+                amount: 3,
+                sp: 7
+            }]
+        );
+        assert_eq!(enemies[0].target().hp(), 95000.0);
+    }
 }
@@ -0,0 +1,113 @@
+use crate::buff::{Buff, BuffLedger};
+use std::fmt::{self, Display, Formatter};
+
+pub const SP_LIMIT: i32 = 99;
+
+#[derive(Debug, Clone)]
+pub struct SpPool {
+    current: i32,
+    cap: i32,
+    regen: i32,
+    cost_down: BuffLedger,
+}
+
+impl Default for SpPool {
+    fn default() -> Self {
+        Self::new(4, 20, 2)
+    }
+}
+
+impl SpPool {
+    pub fn new(current: i32, cap: i32, regen: i32) -> Self {
+        Self {
+            current,
+            cap,
+            regen,
+            cost_down: BuffLedger::new(),
+        }
+    }
+
+    pub fn current(&self) -> i32 {
+        self.current
+    }
+
+    pub fn cap(&self) -> i32 {
+        self.cap
+    }
+
+    pub fn regen(&self) -> i32 {
+        self.regen
+    }
+
+    pub fn cost(&self, base: i32) -> i32 {
+        (base - self.cost_down.total().round() as i32).max(0)
+    }
+
+    pub fn can_afford(&self, base: i32) -> bool {
+        self.cost(base) <= self.current
+    }
+
+    pub fn pay(&mut self, base: i32) -> bool {
+        let cost = self.cost(base);
+        if cost > self.current {
+            return false;
+        }
+        self.current -= cost;
+        true
+    }
+
+    // per-turn regeneration stops at the cap but never takes SP away
+    pub fn regenerate(&mut self) {
+        if self.current < self.cap {
+            self.current = (self.current + self.regen).min(self.cap);
+        }
+    }
+
+    // SP-up skills and overdrive bonuses ignore the cap, only SP_LIMIT applies
+    pub fn gain(&mut self, amount: i32) {
+        self.current = (self.current + amount).clamp(0, SP_LIMIT);
+    }
+
+    pub fn reduce_cost(&mut self, buff: Buff) {
+        self.cost_down.apply(buff);
+    }
+
+    pub fn tick(&mut self) {
+        self.cost_down.tick();
+    }
+}
+
+impl Display for SpPool {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "SP {}/{} (+{})", self.current, self.cap, self.regen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buff::BuffCategory;
+
+    #[test]
+    fn regen_and_gain() {
+        let mut pool = SpPool::new(16, 20, 2);
+        pool.regenerate();
+        pool.regenerate();
+        pool.regenerate();
+        assert_eq!(pool.current(), 20);
+        pool.gain(5);
+        pool.regenerate();
+        assert_eq!(pool.current(), 25);
+        assert!(!pool.pay(26));
+        pool.reduce_cost(Buff::new(
+            "cost down",
+            BuffCategory::SkillBuff,
+            2.0,
+            Some(1),
+        ));
+        assert!(pool.pay(26));
+        assert_eq!(pool.current(), 1);
+        pool.tick();
+        assert_eq!(pool.cost(26), 26);
+    }
+}
@@ -0,0 +1,532 @@
+use crate::ability::{self, AbilityModel, ModifierCell};
+use crate::buff::StatBuffs;
+use crate::element::{AttackType, Element};
+use crate::pattern::Pattern;
+use crate::skill::{DamageCalculator, Skill, SkillEffect};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+#[derive(Debug, Clone)]
+pub struct Target {
+    name: String,
+    max_dp: f32,
+    dp: f32,
+    max_hp: f32,
+    hp: f32,
+    destruction: f32,
+    max_destruction: f32,
+    element_rates: HashMap<Element, f32>,
+    attack_type_rates: HashMap<AttackType, f32>,
+    recovery: Option<(u32, f32)>,
+    broken_turns: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Hit {
+    pub dp_damage: f32,
+    pub hp_damage: f32,
+    pub destruction: f32,
+    pub broke: bool,
+    pub critical: bool,
+}
+
+pub struct Enemy {
+    target: Target,
+    defense: AbilityModel,
+    luck: Option<AbilityModel>,
+    buffs: StatBuffs,
+    attack: Option<AbilityModel>,
+    skills: Vec<Skill>,
+    pattern: Pattern,
+}
+
+impl Target {
+    pub fn new(name: &str, dp: f32, hp: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            max_dp: dp,
+            dp,
+            max_hp: hp,
+            hp,
+            destruction: 1.0,
+            max_destruction: 3.0,
+            element_rates: HashMap::new(),
+            attack_type_rates: HashMap::new(),
+            recovery: None,
+            broken_turns: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn dp(&self) -> f32 {
+        self.dp
+    }
+
+    pub fn max_dp(&self) -> f32 {
+        self.max_dp
+    }
+
+    pub fn hp(&self) -> f32 {
+        self.hp
+    }
+
+    pub fn max_hp(&self) -> f32 {
+        self.max_hp
+    }
+
+    pub fn destruction(&self) -> f32 {
+        self.destruction
+    }
+
+    pub fn max_destruction(&self) -> f32 {
+        self.max_destruction
+    }
+
+    pub fn set_max_destruction(&mut self, rate: f32) {
+        self.max_destruction = rate;
+        self.destruction = self.destruction.min(rate);
+    }
+
+    // 2.0 is a weakness, 0.5 a resistance, anything undeclared is 1.0
+    pub fn set_element_rate(&mut self, element: Element, rate: f32) {
+        self.element_rates.insert(element, rate);
+    }
+
+    pub fn set_attack_type_rate(&mut self, attack_type: AttackType, rate: f32) {
+        self.attack_type_rates.insert(attack_type, rate);
+    }
+
+    pub fn element_rate(&self, element: Element) -> f32 {
+        self.element_rates.get(&element).copied().unwrap_or(1.0)
+    }
+
+    pub fn attack_type_rate(&self, attack_type: Option<AttackType>) -> f32 {
+        attack_type
+            .and_then(|attack_type| self.attack_type_rates.get(&attack_type).copied())
+            .unwrap_or(1.0)
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hp <= 0.0
+    }
+
+    // 0 without HP to track
+    pub fn hp_rate(&self) -> f32 {
+        if self.max_hp > 0.0 {
+            self.hp / self.max_hp
+        } else {
+            0.0
+        }
+    }
+
+    // a target without DP counts as broken from the start
+    pub fn is_broken(&self) -> bool {
+        self.dp <= 0.0
+    }
+
+    pub fn recovery(&self) -> Option<(u32, f32)> {
+        self.recovery
+    }
+
+    // after turns broken, DP comes back at dp_rate of the maximum and destruction resets
+    pub fn set_recovery(&mut self, recovery: Option<(u32, f32)>) {
+        self.recovery = recovery;
+    }
+
+    // counts the broken turns, returning whether the DP came back
+    pub fn tick(&mut self) -> bool {
+        if !self.is_broken() || self.max_dp <= 0.0 || self.is_dead() {
+            self.broken_turns = 0;
+            return false;
+        }
+        self.broken_turns += 1;
+        match self.recovery {
+            Some((turns, dp_rate)) if self.broken_turns >= turns => {
+                self.dp = self.max_dp * dp_rate;
+                self.destruction = 1.0;
+                self.broken_turns = 0;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // roll gives the calculator of each hit and whether it was a critical hit
+    pub fn hit(
+        &mut self,
+        effect: &SkillEffect,
+        attacker_model: &AbilityModel,
+        defender_model: &AbilityModel,
+        count: u32,
+        roll: impl FnMut() -> (DamageCalculator, bool),
+    ) -> Vec<Hit> {
+        let base = effect.damage_to_enemy(attacker_model, defender_model);
+        self.apply_rolled(effect, base, count, roll)
+    }
+
+    // one hit carrying the whole damage of the effect
+    pub fn apply(&mut self, effect: &SkillEffect, base: f32, calculator: &DamageCalculator) -> Hit {
+        self.strike(effect, base, 1.0, calculator)
+    }
+
+    // hits land one after another, so the later ones see the destruction the earlier ones built
+    pub fn apply_hits(
+        &mut self,
+        effect: &SkillEffect,
+        base: f32,
+        count: u32,
+        calculator: &DamageCalculator,
+    ) -> Vec<Hit> {
+        self.apply_rolled(effect, base, count, || (calculator.clone(), false))
+    }
+
+    // apply_hits with every hit rolling its own spread and critical
+    pub fn apply_rolled(
+        &mut self,
+        effect: &SkillEffect,
+        base: f32,
+        count: u32,
+        mut roll: impl FnMut() -> (DamageCalculator, bool),
+    ) -> Vec<Hit> {
+        let mut hits = vec![];
+        for share in effect.hit_shares(count) {
+            if self.is_dead() {
+                break;
+            }
+            let (calculator, critical) = roll();
+            let mut hit = self.strike(effect, base, share, &calculator);
+            hit.critical = critical;
+            hits.push(hit);
+        }
+        hits
+    }
+
+    // share is the part of the effect's damage and destruction this hit carries
+    fn strike(
+        &mut self,
+        effect: &SkillEffect,
+        base: f32,
+        share: f32,
+        calculator: &DamageCalculator,
+    ) -> Hit {
+        let bonus = if self.is_broken() {
+            effect.break_bonus()
+        } else {
+            1.0
+        };
+        let base = base * share * bonus;
+        let unbroken = !self.is_broken();
+        let mut calculator = calculator.clone();
+        calculator.set_element_rate(self.element_rate(effect.element()));
+        calculator.set_weapon_rate(self.attack_type_rate(effect.attack_type()));
+        let mut dp_damage = 0.0;
+        let mut hp_damage = 0.0;
+        // share of the hit left over for HP once DP is gone
+        let mut remaining = 1.0;
+        if self.dp > 0.0 {
+            calculator.set_destruction(1.0);
+            let damage = calculator.calculate(base) * effect.dp_rate();
+            if damage < self.dp {
+                dp_damage = damage;
+                remaining = 0.0;
+            } else {
+                dp_damage = self.dp;
+                remaining = if damage > 0.0 {
+                    (damage - self.dp) / damage
+                } else {
+                    0.0
+                };
+            }
+            self.dp -= dp_damage;
+        }
+        if remaining > 0.0 && self.hp > 0.0 {
+            calculator.set_destruction(self.destruction);
+            let damage = calculator.calculate(base) * effect.hp_rate() * remaining;
+            hp_damage = damage.min(self.hp);
+            self.hp -= hp_damage;
+            self.destruction =
+                (self.destruction + effect.destruction() * share).min(self.max_destruction);
+        }
+        Hit {
+            dp_damage,
+            hp_damage,
+            destruction: self.destruction,
+            broke: unbroken && self.is_broken(),
+            critical: false,
+        }
+    }
+}
+
+impl Clone for Enemy {
+    fn clone(&self) -> Self {
+        let mut copies = vec![];
+        Self {
+            target: self.target.clone(),
+            defense: self.defense.detached_with(&mut copies),
+            luck: self
+                .luck
+                .as_ref()
+                .map(|luck| luck.detached_with(&mut copies)),
+            buffs: self.buffs.clone(),
+            attack: self
+                .attack
+                .as_ref()
+                .map(|attack| attack.detached_with(&mut copies)),
+            skills: self.skills.clone(),
+            pattern: self.pattern.clone(),
+        }
+    }
+}
+
+impl Enemy {
+    pub fn new(target: Target, defense: AbilityModel) -> Self {
+        Self {
+            target,
+            defense,
+            luck: None,
+            buffs: StatBuffs::new(),
+            attack: None,
+            skills: vec![],
+            pattern: Pattern::default(),
+        }
+    }
+
+    pub fn set_luck(&mut self, luck: AbilityModel) {
+        self.luck = Some(luck);
+    }
+
+    // an enemy without an attack model never acts
+    pub fn set_attack(&mut self, attack: AbilityModel) {
+        self.attack = Some(attack);
+    }
+
+    pub fn attack(&self) -> Option<&AbilityModel> {
+        self.attack.as_ref()
+    }
+
+    pub fn add_skill(&mut self, skill: Skill) {
+        self.skills.push(skill);
+    }
+
+    pub fn skills(&self) -> &[Skill] {
+        &self.skills
+    }
+
+    pub fn set_pattern(&mut self, pattern: Pattern) {
+        self.pattern = pattern;
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn pattern_mut(&mut self) -> &mut Pattern {
+        &mut self.pattern
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.target
+    }
+
+    pub fn defense(&self) -> &AbilityModel {
+        &self.defense
+    }
+
+    pub fn luck(&self) -> Option<&AbilityModel> {
+        self.luck.as_ref()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.target.is_dead()
+    }
+
+    // debuffs such as defense down
+    pub fn buffs(&self) -> &StatBuffs {
+        &self.buffs
+    }
+
+    pub fn buffs_mut(&mut self) -> &mut StatBuffs {
+        &mut self.buffs
+    }
+
+    pub fn hit(
+        &mut self,
+        effect: &SkillEffect,
+        attacker_model: &AbilityModel,
+        count: u32,
+        roll: impl FnMut() -> (DamageCalculator, bool),
+    ) -> Vec<Hit> {
+        self.target
+            .hit(effect, attacker_model, &self.defense, count, roll)
+    }
+
+    // returns whether the DP came back from a break
+    pub fn tick(&mut self) -> bool {
+        self.buffs.tick();
+        let mut cells: Vec<ModifierCell> = vec![];
+        for cell in self
+            .defense
+            .cells()
+            .into_iter()
+            .chain(self.luck.iter().flat_map(|luck| luck.cells()))
+            .chain(self.attack.iter().flat_map(|attack| attack.cells()))
+        {
+            if !cells.iter().any(|known| Arc::ptr_eq(known, &cell)) {
+                cells.push(cell);
+            }
+        }
+        for cell in cells {
+            ability::lock(&cell).tick();
+        }
+        self.target.tick()
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[{}] DP: {}/{}, HP: {}/{}, destruction: {}%",
+            self.name,
+            self.dp,
+            self.max_dp,
+            self.hp,
+            self.max_hp,
+            (self.destruction * 100.0).round()
+        )
+    }
+}
+
+impl Display for Hit {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DP -{}, HP -{} ({}%)",
+            self.dp_damage.round(),
+            self.hp_damage.round(),
+            (self.destruction * 100.0).round()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::{Ability, AbilityModelType, AbilityModifier, AbilityModifierHelper};
+    use crate::skill::BreakCondition;
+
+    #[test]
+    fn dp_then_hp() {
+        let mut target = Target::new("enemy", 1000.0, 5000.0);
+        let mut effect = SkillEffect::new_damage("damage1", 600.0, 150);
+        effect.set_rates(1.0, 2.0);
+        effect.set_destruction(0.5);
+        let mut calculator = DamageCalculator::new();
+        calculator.set_spread(1.0, 1.0);
+        let hit = target.apply(&effect, 600.0, &calculator);
+        assert_eq!(hit.dp_damage, 600.0);
+        assert_eq!(hit.hp_damage, 0.0);
+        assert_eq!(target.destruction(), 1.0);
+        // 400 left on DP, the remaining 1/3 of the hit lands on HP at 2x
+        let hit = target.apply(&effect, 600.0, &calculator);
+        assert_eq!(hit.dp_damage, 400.0);
+        assert_eq!(hit.hp_damage, 400.0);
+        assert_eq!(target.destruction(), 1.5);
+        let hit = target.apply(&effect, 600.0, &calculator);
+        assert_eq!(hit.hp_damage, 1800.0);
+        assert_eq!(target.destruction(), 2.0);
+        assert_eq!(target.hp(), 2800.0);
+    }
+
+    #[test]
+    fn weakness() {
+        let mut target = Target::new("enemy", 0.0, 10000.0);
+        target.set_element_rate(Element::Fire, 2.0);
+        target.set_element_rate(Element::Ice, 0.5);
+        target.set_attack_type_rate(AttackType::Slash, 1.5);
+        let mut calculator = DamageCalculator::new();
+        calculator.set_spread(1.0, 1.0);
+        let mut effect = SkillEffect::new_damage("fire slash", 100.0, 150);
+        effect.set_element(Element::Fire);
+        effect.set_attack_type(Some(AttackType::Slash));
+        assert_eq!(target.apply(&effect, 100.0, &calculator).hp_damage, 300.0);
+        effect.set_element(Element::Ice);
+        effect.set_attack_type(Some(AttackType::Strike));
+        assert_eq!(target.apply(&effect, 100.0, &calculator).hp_damage, 50.0);
+    }
+
+    #[test]
+    fn multi_hit() {
+        let mut calculator = DamageCalculator::new();
+        calculator.set_spread(1.0, 1.0);
+        let mut effect = SkillEffect::new_damage("flurry", 2000.0, 150);
+        effect.set_destruction(0.4);
+        effect.set_hits(4);
+        let mut target = Target::new("enemy", 1000.0, 5000.0);
+        let hits = target.apply_hits(&effect, 2000.0, 4, &calculator);
+        // two hits break the DP, the last two land on HP at 1.0 then 1.1
+        let hp: Vec<f32> = hits.iter().map(|hit| hit.hp_damage).collect();
+        assert_eq!(hp, vec![0.0, 0.0, 500.0, 550.0]);
+        assert_eq!(target.destruction(), 1.2);
+        // the same damage in a single hit carries half of it over to HP at 1.0
+        let mut single = Target::new("enemy", 1000.0, 5000.0);
+        assert_eq!(single.apply(&effect, 2000.0, &calculator).hp_damage, 1000.0);
+        // every hit rolls its own critical
+        let mut target = Target::new("enemy", 0.0, 5000.0);
+        let mut critical = false;
+        let hits = target.apply_rolled(&effect, 2000.0, 2, || {
+            critical = !critical;
+            let mut calculator = calculator.clone();
+            calculator.set_critical(if critical { 1.5 } else { 1.0 });
+            (calculator, critical)
+        });
+        assert_eq!(hits[0].hp_damage, 1500.0);
+        assert!(hits[0].critical);
+        assert_eq!(hits[1].hp_damage, 1200.0);
+        assert!(!hits[1].critical);
+        effect.set_hit_weights(vec![1.0, 1.0, 2.0]);
+        assert_eq!(effect.hit_shares(3), vec![0.25, 0.25, 0.5]);
+        assert_eq!(effect.hit_shares(2), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn break_and_recover() {
+        let mut calculator = DamageCalculator::new();
+        calculator.set_spread(1.0, 1.0);
+        let mut effect = SkillEffect::new_damage("hit", 1500.0, 0);
+        effect.set_break_bonus(2.0);
+        let mut target = Target::new("enemy", 1000.0, 10000.0);
+        target.set_recovery(Some((2, 0.5)));
+        assert!(BreakCondition::Unbroken.matches(target.is_broken()));
+        assert!(target.apply(&effect, 1500.0, &calculator).broke);
+        assert!(BreakCondition::Broken.matches(target.is_broken()));
+        // the bonus applies from the hit after the break
+        assert_eq!(target.apply(&effect, 1500.0, &calculator).hp_damage, 3000.0);
+        assert!(!target.tick());
+        assert!(target.tick());
+        assert_eq!(target.dp(), 500.0);
+        assert!(!target.is_broken());
+    }
+
+    #[test]
+    fn clone_keeps_sharing() {
+        let stamina: AbilityModifierHelper = AbilityModifier::from(Ability::Stamina(100)).into();
+        let model = || {
+            AbilityModel::new(AbilityModelType::Single, stamina.get_cell(), None)
+                .expect("it should succeed")
+        };
+        let mut enemy = Enemy::new(Target::new("enemy", 0.0, 1000.0), model());
+        enemy.set_luck(model());
+        let copy = enemy.clone();
+        ability::lock(&copy.defense().cells()[0]).apply_positive(50);
+        assert_eq!(copy.luck().expect("it should exist").value(), 150);
+        assert_eq!(enemy.luck().expect("it should exist").value(), 100);
+    }
+}
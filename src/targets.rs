@@ -0,0 +1,526 @@
+use crate::battle::FRONT_SIZE;
+use crate::buff::{Buff, BuffCategory, BuffStat};
+use crate::character::Character;
+use crate::critical::Critical;
+use crate::field::{Battlefield, Layer};
+use crate::sampling::Rng;
+use crate::skill::{DamageCalculator, EffectKind, Pool, Scope, Skill, SkillEffect};
+use crate::target::{Enemy, Hit};
+use serde::Serialize;
+
+// everything a skill can act on, actors and allies are indices into party
+pub struct Targets<'a> {
+    party: &'a mut [Character],
+    enemies: &'a mut [Enemy],
+    front: Vec<usize>,
+    enemy: usize,
+    ally: Option<usize>,
+    calculator: DamageCalculator,
+    critical: Critical,
+    rng: Option<&'a mut Rng>,
+    battlefield: Option<&'a mut Battlefield>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    Damage {
+        target: String,
+        // index of the enemy among the enemies of the battle
+        enemy: usize,
+        hits: Vec<Hit>,
+    },
+    Sp {
+        target: String,
+        amount: i32,
+        sp: i32,
+    },
+    SpCostDown {
+        target: String,
+        amount: i32,
+        duration: Option<u32>,
+    },
+    Buff {
+        target: String,
+        kind: EffectKind,
+        amount: f32,
+        duration: Option<u32>,
+    },
+    Heal {
+        target: String,
+        pool: Pool,
+        amount: f32,
+        restored: f32,
+        current: f32,
+    },
+    Zone {
+        layer: Layer,
+        rate: f32,
+        duration: Option<u32>,
+        replaced: Option<String>,
+    },
+}
+
+// what one effect of a skill did, one outcome per target it reached
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectRecord {
+    pub effect: String,
+    pub kind: EffectKind,
+    pub outcomes: Vec<Outcome>,
+}
+
+impl<'a> Targets<'a> {
+    pub fn new(party: &'a mut [Character], enemies: &'a mut [Enemy]) -> Self {
+        Self {
+            front: (0..party.len().min(FRONT_SIZE)).collect(),
+            party,
+            enemies,
+            enemy: 0,
+            ally: None,
+            calculator: DamageCalculator::new(),
+            critical: Critical::new(),
+            rng: None,
+            battlefield: None,
+        }
+    }
+
+    pub fn party(&self) -> &[Character] {
+        self.party
+    }
+
+    pub fn enemies(&self) -> &[Enemy] {
+        self.enemies
+    }
+
+    // the members reached by AllAllies
+    pub fn set_front(&mut self, front: Vec<usize>) {
+        self.front = front;
+        self.front.retain(|&member| member < self.party.len());
+    }
+
+    pub fn set_enemy(&mut self, enemy: usize) {
+        self.enemy = enemy;
+    }
+
+    // the actor itself when not set
+    pub fn set_ally(&mut self, ally: Option<usize>) {
+        self.ally = ally;
+    }
+
+    pub fn set_calculator(&mut self, calculator: DamageCalculator) {
+        self.calculator = calculator;
+    }
+
+    pub fn set_critical(&mut self, critical: Critical) {
+        self.critical = critical;
+    }
+
+    // spread, criticals and hit counts are rolled from rng, expected values are used without one
+    pub fn set_rng(&mut self, rng: Option<&'a mut Rng>) {
+        self.rng = rng;
+    }
+
+    // read for the field rate of every hit, zone effects do nothing without one
+    pub fn set_battlefield(&mut self, battlefield: Option<&'a mut Battlefield>) {
+        self.battlefield = battlefield;
+    }
+
+    // applies the effects of the skill in order, SP is not paid here,
+    // None when the actor is not in the party
+    pub fn execute(&mut self, actor: usize, skill: &Skill) -> Option<Vec<EffectRecord>> {
+        if actor >= self.party.len() {
+            return None;
+        }
+        Some(
+            skill
+                .effects()
+                .map(|effect| EffectRecord {
+                    effect: effect.name().to_owned(),
+                    kind: effect.kind(),
+                    outcomes: self.resolve(actor, effect),
+                })
+                .collect(),
+        )
+    }
+
+    // enemies in the scope of the effect that meet its break condition
+    fn enemy_indices(&self, effect: &SkillEffect) -> Vec<usize> {
+        let indices = match effect.scope() {
+            Scope::AllEnemies => (0..self.enemies.len())
+                .filter(|&index| !self.enemies[index].is_dead())
+                .collect(),
+            _ if self.enemy < self.enemies.len() => vec![self.enemy],
+            _ => vec![],
+        };
+        indices
+            .into_iter()
+            .filter(|&index| {
+                effect.condition().is_none_or(|condition| {
+                    condition.matches(self.enemies[index].target().is_broken())
+                })
+            })
+            .collect()
+    }
+
+    fn ally_indices(&self, scope: Scope, actor: usize) -> Vec<usize> {
+        match scope {
+            Scope::Actor => vec![actor],
+            Scope::Ally => vec![self.ally.unwrap_or(actor)],
+            Scope::AllAllies => self.front.clone(),
+            Scope::Enemy | Scope::AllEnemies => vec![],
+        }
+    }
+
+    fn resolve(&mut self, actor: usize, effect: &SkillEffect) -> Vec<Outcome> {
+        // enemy scoped effects check each enemy they reach, the rest the main enemy
+        if let (Some(condition), false) = (effect.condition(), effect.scope().is_enemy()) {
+            let enemy = self.enemies.get(self.enemy).map(|enemy| enemy.target());
+            if !enemy.is_some_and(|enemy| condition.matches(enemy.is_broken())) {
+                return vec![];
+            }
+        }
+        match effect.kind() {
+            EffectKind::Damage => self.damage(actor, effect),
+            EffectKind::Sp | EffectKind::SpCostDown => self.support(actor, effect),
+            EffectKind::Buff(_) | EffectKind::AbilityUp(_) | EffectKind::AbilityDown(_) => {
+                self.buff(actor, effect)
+            }
+            EffectKind::Heal(pool) => self.heal(actor, effect, pool),
+            EffectKind::Zone(layer) => self.zone(actor, effect, layer),
+        }
+    }
+
+    // heals and regeneration scale off the heal model, everything else off attack
+    fn magnitude(&self, actor: usize, effect: &SkillEffect) -> f32 {
+        let member = &self.party[actor];
+        match (effect.model(), effect.kind()) {
+            (Some(formula), _) => effect.effect_oneside(&member.model(formula)),
+            (None, EffectKind::Heal(_) | EffectKind::Buff(BuffStat::Regen(_))) => {
+                effect.effect_oneside(member.heal())
+            }
+            (None, _) => effect.effect_oneside(member.attack()),
+        }
+    }
+
+    fn damage(&mut self, actor: usize, effect: &SkillEffect) -> Vec<Outcome> {
+        let member = &self.party[actor];
+        let effect = &member.arm(effect);
+        let mut outcomes = vec![];
+        for index in self.enemy_indices(effect) {
+            let enemy = &mut self.enemies[index];
+            let mut calculator = self.calculator.clone();
+            let mut critical = self.critical.clone();
+            member
+                .buffs()
+                .modify_attack(effect.element(), &mut calculator, &mut critical);
+            enemy
+                .buffs()
+                .modify_defense(effect.element(), &mut calculator);
+            if let Some(battlefield) = self.battlefield.as_deref() {
+                calculator.add_field(battlefield.rate(effect.element()));
+            }
+            let rate = member.critical_rate(&critical, enemy);
+            let count = effect.roll_hits(self.rng.as_deref_mut());
+            let rng = &mut self.rng;
+            let hits = enemy.hit(effect, member.attack(), count, || {
+                let mut calculator = calculator.clone();
+                calculator.set_roll(rng.as_deref_mut().map(Rng::next_f32));
+                let rolled = rng.as_deref_mut().map(Rng::next_f32);
+                let hit = critical.apply(&mut calculator, rate, rolled);
+                (calculator, hit)
+            });
+            outcomes.push(Outcome::Damage {
+                target: enemy.target().name().to_owned(),
+                enemy: index,
+                hits,
+            });
+        }
+        outcomes
+    }
+
+    fn zone(&mut self, actor: usize, effect: &SkillEffect, layer: Layer) -> Vec<Outcome> {
+        let rate = self.magnitude(actor, effect);
+        let Some(battlefield) = self.battlefield.as_deref_mut() else {
+            return vec![];
+        };
+        vec![Self::lay_zone(battlefield, effect, layer, rate)]
+    }
+
+    // the zone of the effect at rate, enemies lay theirs through here too
+    pub fn lay_zone(
+        battlefield: &mut Battlefield,
+        effect: &SkillEffect,
+        layer: Layer,
+        rate: f32,
+    ) -> Outcome {
+        let replaced = battlefield.lay(layer, effect.zone(rate));
+        Outcome::Zone {
+            layer,
+            rate,
+            duration: effect.duration(),
+            replaced: replaced.map(|zone| zone.name().to_owned()),
+        }
+    }
+
+    fn support(&mut self, actor: usize, effect: &SkillEffect) -> Vec<Outcome> {
+        let amount = self.magnitude(actor, effect).round() as i32;
+        let mut outcomes = vec![];
+        for index in self.ally_indices(effect.scope(), actor) {
+            let Some(member) = self.party.get_mut(index) else {
+                continue;
+            };
+            let target = member.name().to_owned();
+            match effect.kind() {
+                EffectKind::Sp => {
+                    member.sp_mut().gain(amount);
+                    outcomes.push(Outcome::Sp {
+                        target,
+                        amount,
+                        sp: member.sp().current(),
+                    });
+                }
+                EffectKind::SpCostDown => {
+                    member.sp_mut().reduce_cost(Buff::new(
+                        effect.name(),
+                        BuffCategory::SkillBuff,
+                        amount as f32,
+                        effect.duration(),
+                    ));
+                    outcomes.push(Outcome::SpCostDown {
+                        target,
+                        amount,
+                        duration: effect.duration(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        outcomes
+    }
+
+    fn heal(&mut self, actor: usize, effect: &SkillEffect, pool: Pool) -> Vec<Outcome> {
+        let amount = self.magnitude(actor, effect);
+        let mut outcomes = vec![];
+        for index in self.ally_indices(effect.scope(), actor) {
+            let Some(member) = self.party.get_mut(index) else {
+                continue;
+            };
+            if member.is_dead() {
+                continue;
+            }
+            let restored = member.vitals_mut().restore(pool, amount, effect.overheal());
+            outcomes.push(Outcome::Heal {
+                target: member.name().to_owned(),
+                pool,
+                amount,
+                restored,
+                current: member.vitals().current(pool),
+            });
+        }
+        outcomes
+    }
+
+    // enemies have no named abilities, ability buffs only reach the party
+    fn buff(&mut self, actor: usize, effect: &SkillEffect) -> Vec<Outcome> {
+        let amount = self.magnitude(actor, effect);
+        let buff = |value: f32| {
+            Buff::new(
+                effect.name(),
+                BuffCategory::SkillBuff,
+                value,
+                effect.duration(),
+            )
+        };
+        let mut outcomes = vec![];
+        let mut push = |target: &str| {
+            outcomes.push(Outcome::Buff {
+                target: target.to_owned(),
+                kind: effect.kind(),
+                amount,
+                duration: effect.duration(),
+            })
+        };
+        match effect.scope() {
+            Scope::Enemy | Scope::AllEnemies => {
+                let EffectKind::Buff(stat) = effect.kind() else {
+                    return vec![];
+                };
+                for index in self.enemy_indices(effect) {
+                    let enemy = &mut self.enemies[index];
+                    enemy.buffs_mut().apply(stat, buff(amount));
+                    push(enemy.target().name());
+                }
+            }
+            scope => {
+                for index in self.ally_indices(scope, actor) {
+                    let Some(member) = self.party.get_mut(index) else {
+                        continue;
+                    };
+                    match effect.kind() {
+                        EffectKind::Buff(stat) => member.buffs_mut().apply(stat, buff(amount)),
+                        EffectKind::AbilityUp(ability) => member
+                            .modifier(ability)
+                            .get_mut()
+                            .apply(buff(amount.round())),
+                        EffectKind::AbilityDown(ability) => member
+                            .modifier(ability)
+                            .get_mut()
+                            .apply(buff(-amount.round())),
+                        _ => continue,
+                    }
+                    push(member.name());
+                }
+            }
+        }
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ability::{Ability, AbilityName, ModelFormula};
+    use crate::character::Formula;
+    use crate::skill::BreakCondition;
+    use crate::target::Target;
+    use crate::testing::{enemy, member, single};
+
+    #[test]
+    fn break_conditions() {
+        // a break condition is checked against every enemy the effect reaches
+        let mut party = vec![member("a", 200)];
+        let mut finisher = Skill::new("finisher");
+        let mut sweep = SkillEffect::new_damage("finisher", 1000.0, 100);
+        sweep.set_scope(Scope::AllEnemies);
+        sweep.set_condition(Some(BreakCondition::Broken));
+        finisher.add_effect(sweep);
+        let mut enemies = vec![enemy(1000.0, 100000.0), enemy(0.0, 100000.0)];
+        let mut targets = Targets::new(&mut party, &mut enemies);
+        let records = targets.execute(0, &finisher).expect("it should succeed");
+        assert_eq!(records[0].outcomes.len(), 1);
+        assert_eq!(enemies[0].target().dp(), 1000.0);
+        assert!(enemies[1].target().hp() < 100000.0);
+    }
+
+    #[test]
+    fn buffs_and_debuffs() {
+        let formula = Formula {
+            attack: ModelFormula::single(AbilityName::Strength),
+            ..Formula::default()
+        };
+        let mut support = Skill::new("support");
+        let mut attack_up = SkillEffect::new_buff(
+            "attack up",
+            EffectKind::Buff(BuffStat::AttackUp),
+            0.3,
+            0.5,
+            200,
+        );
+        attack_up.set_scope(Scope::Ally);
+        attack_up.set_duration(Some(2));
+        support.add_effect(attack_up);
+        let mut defense_down = SkillEffect::new_buff(
+            "defense down",
+            EffectKind::Buff(BuffStat::DefenseDown),
+            0.5,
+            0.5,
+            0,
+        );
+        defense_down.set_duration(Some(1));
+        support.add_effect(defense_down);
+        let mut strength_up = SkillEffect::new_buff(
+            "strength up",
+            EffectKind::AbilityUp(AbilityName::Strength),
+            20.0,
+            60.0,
+            200,
+        );
+        strength_up.set_model(Some(ModelFormula::single(AbilityName::Intelligence)));
+        strength_up.set_scope(Scope::Ally);
+        support.add_effect(strength_up);
+        let mut attack = Skill::new("attack");
+        attack.add_effect(SkillEffect::new_damage("attack", 1000.0, 100));
+        let mut party = vec![
+            Character::new("a", [200, 0, 0, 0, 0, 0], &formula),
+            Character::new("b", [100, 0, 0, 0, 0, 100], &formula),
+        ];
+        let mut enemies = vec![enemy(0.0, 100000.0)];
+        let mut targets = Targets::new(&mut party, &mut enemies);
+        let mut critical = Critical::new();
+        critical.set_base_rate(0.0, 0.0);
+        targets.set_critical(critical);
+        targets.set_ally(Some(0));
+        let records = targets.execute(1, &support).expect("it should succeed");
+        // 0.3 up to 0.5 at 200 Strength, the caster has 100
+        assert!(matches!(
+            records[0].outcomes[..],
+            [Outcome::Buff { amount, .. }] if amount == 0.4
+        ));
+        // 20 up to 60 at 200 Intelligence, the caster has 100
+        assert_eq!(targets.party()[0].attack().value(), 240);
+        targets.set_ally(None);
+        let records = targets.execute(0, &attack).expect("it should succeed");
+        // at the border again, x1.4 attack and x1.5 defense down
+        assert!(matches!(
+            &records[0].outcomes[..],
+            [Outcome::Damage { hits, .. }] if hits[0].hp_damage == 5000.0 * 1.4 * 1.5
+        ));
+        party[0].tick();
+        enemies[0].tick();
+        assert_eq!(party[0].buffs().total(BuffStat::AttackUp), 0.4);
+        assert_eq!(enemies[0].buffs().total(BuffStat::DefenseDown), 0.0);
+    }
+
+    #[test]
+    fn heal_and_regen() {
+        let mut heal = Skill::new("heal");
+        let mut recover = SkillEffect::new_heal("heal", Pool::Dp, 300.0, 200);
+        recover.set_overheal(1.5);
+        heal.add_effect(recover);
+        let mut regen = SkillEffect::new_buff(
+            "regen",
+            EffectKind::Buff(BuffStat::Regen(Pool::Hp)),
+            100.0,
+            100.0,
+            0,
+        );
+        regen.set_scope(Scope::Ally);
+        regen.set_duration(Some(2));
+        heal.add_effect(regen);
+        let mut party = vec![
+            Character::new("a", [0; 6], &Formula::default()),
+            Character::new("b", [0, 0, 0, 100, 0, 100], &Formula::default()),
+        ];
+        party[0].set_vitals(1000.0, 2000.0);
+        // breaks the DP, a third of the hit carries over to HP
+        party[0].vitals_mut().apply_rolled(
+            &SkillEffect::new_damage("hit", 1500.0, 0),
+            1500.0,
+            1,
+            || (DamageCalculator::new(), false),
+        );
+        let mut enemies = vec![Enemy::new(
+            Target::new("enemy", 0.0, 1.0),
+            single(Ability::Stamina(0)),
+        )];
+        let mut targets = Targets::new(&mut party, &mut enemies);
+        targets.set_ally(Some(0));
+        // 300 up to 900 at 200 of the heal model, the healer has 100
+        let restored: Vec<f32> = (0..3)
+            .map(
+                |_| match &targets.execute(1, &heal).expect("it should succeed")[0].outcomes[..] {
+                    [Outcome::Heal { restored, .. }] => *restored,
+                    outcomes => panic!("unexpected outcomes {:?}", outcomes),
+                },
+            )
+            .collect();
+        assert_eq!(restored, vec![600.0, 600.0, 300.0]);
+        assert_eq!(party[0].vitals().dp(), 1500.0);
+        // three casts, only the top two regenerations count
+        assert_eq!(party[0].regenerate(), vec![(Pool::Hp, 200.0)]);
+        party[0].tick();
+        party[0].tick();
+        assert_eq!(party[0].regenerate(), vec![]);
+        assert_eq!(party[0].vitals().hp(), 1700.0);
+    }
+}
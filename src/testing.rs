@@ -0,0 +1,32 @@
+use crate::ability::{
+    Ability, AbilityModel, AbilityModelType, AbilityModifier, AbilityModifierHelper, AbilityName,
+    ModelFormula,
+};
+use crate::character::{Character, Formula};
+use crate::skill::{Skill, SkillEffect};
+use crate::target::{Enemy, Target};
+
+// a model over a lone ability with a cell of its own
+pub fn single(ability: Ability) -> AbilityModel {
+    let helper: AbilityModifierHelper = AbilityModifier::from(ability).into();
+    AbilityModel::new(AbilityModelType::Single, helper.get_cell(), None)
+        .expect("a single model needs one ability")
+}
+
+// attacks off Strength alone, with a 1000 damage skill at a border of 100
+pub fn member(name: &str, strength: i32) -> Character {
+    let formula = Formula {
+        attack: ModelFormula::single(AbilityName::Strength),
+        ..Formula::default()
+    };
+    let mut member = Character::new(name, [strength, 0, 0, 0, 0, 0], &formula);
+    let mut skill = Skill::new("attack");
+    skill.add_effect(SkillEffect::new_damage("attack", 1000.0, 100));
+    member.add_skill(skill);
+    member
+}
+
+// defends with 100 Stamina
+pub fn enemy(dp: f32, hp: f32) -> Enemy {
+    Enemy::new(Target::new("enemy", dp, hp), single(Ability::Stamina(100)))
+}
@@ -0,0 +1,154 @@
+use crate::skill::{DamageCalculator, Pool, SkillEffect};
+use crate::target::Hit;
+use std::fmt::{self, Display, Formatter};
+
+// DP and HP of a party member, hits land on DP first and carry over to HP,
+// without the destruction, weakness and break of an enemy
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Vitals {
+    max_dp: f32,
+    dp: f32,
+    max_hp: f32,
+    hp: f32,
+}
+
+impl Vitals {
+    pub fn new(dp: f32, hp: f32) -> Self {
+        Self {
+            max_dp: dp,
+            dp,
+            max_hp: hp,
+            hp,
+        }
+    }
+
+    pub fn dp(&self) -> f32 {
+        self.dp
+    }
+
+    pub fn max_dp(&self) -> f32 {
+        self.max_dp
+    }
+
+    pub fn hp(&self) -> f32 {
+        self.hp
+    }
+
+    pub fn max_hp(&self) -> f32 {
+        self.max_hp
+    }
+
+    // nothing is tracked without HP
+    pub fn is_tracked(&self) -> bool {
+        self.max_hp > 0.0
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.is_tracked() && self.hp <= 0.0
+    }
+
+    // 0 without HP to track
+    pub fn hp_rate(&self) -> f32 {
+        if self.is_tracked() {
+            self.hp / self.max_hp
+        } else {
+            0.0
+        }
+    }
+
+    pub fn current(&self, pool: Pool) -> f32 {
+        match pool {
+            Pool::Dp => self.dp,
+            Pool::Hp => self.hp,
+        }
+    }
+
+    // fills up to cap times the maximum, a pool already above it is left alone
+    pub fn restore(&mut self, pool: Pool, amount: f32, cap: f32) -> f32 {
+        let (current, max) = match pool {
+            Pool::Dp => (&mut self.dp, self.max_dp),
+            Pool::Hp => (&mut self.hp, self.max_hp),
+        };
+        let restored = ((*current + amount).min(max * cap) - *current).max(0.0);
+        *current += restored;
+        restored
+    }
+
+    // roll gives the calculator of each hit and whether it was a critical hit
+    pub fn apply_rolled(
+        &mut self,
+        effect: &SkillEffect,
+        base: f32,
+        count: u32,
+        mut roll: impl FnMut() -> (DamageCalculator, bool),
+    ) -> Vec<Hit> {
+        let mut hits = vec![];
+        for share in effect.hit_shares(count) {
+            if self.is_dead() {
+                break;
+            }
+            let (calculator, critical) = roll();
+            let damage = calculator.calculate(base * share);
+            let mut hit = Hit {
+                dp_damage: 0.0,
+                hp_damage: 0.0,
+                destruction: 1.0,
+                broke: false,
+                critical,
+            };
+            // share of the hit left over for HP once DP is gone
+            let mut remaining = 1.0;
+            if self.dp > 0.0 {
+                let damage = damage * effect.dp_rate();
+                hit.dp_damage = damage.min(self.dp);
+                remaining = if damage > self.dp {
+                    (damage - self.dp) / damage
+                } else {
+                    0.0
+                };
+                self.dp -= hit.dp_damage;
+            }
+            if remaining > 0.0 {
+                hit.hp_damage = (damage * effect.hp_rate() * remaining).min(self.hp);
+                self.hp -= hit.hp_damage;
+            }
+            hits.push(hit);
+        }
+        hits
+    }
+}
+
+impl Display for Vitals {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DP: {}/{}, HP: {}/{}",
+            self.dp, self.max_dp, self.hp, self.max_hp
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore() {
+        let mut vitals = Vitals::new(1000.0, 2000.0);
+        let calculator = DamageCalculator::new();
+        vitals.apply_rolled(
+            &SkillEffect::new_damage("hit", 1500.0, 0),
+            1500.0,
+            1,
+            || (calculator.clone(), false),
+        );
+        assert_eq!(vitals.restore(Pool::Hp, 300.0, 1.0), 300.0);
+        assert_eq!(vitals.restore(Pool::Hp, 1000.0, 1.0), 200.0);
+        assert_eq!(vitals.restore(Pool::Dp, 1200.0, 1.5), 1200.0);
+        assert_eq!(vitals.restore(Pool::Dp, 1000.0, 1.5), 300.0);
+        // over the cap a regular heal restores nothing and takes nothing away
+        assert_eq!(vitals.restore(Pool::Dp, 1000.0, 1.0), 0.0);
+        assert_eq!(vitals.current(Pool::Dp), 1500.0);
+        assert!(!Vitals::default().is_dead());
+    }
+}